@@ -0,0 +1,53 @@
+use std::fs::read_dir;
+use std::time::{Duration, Instant};
+use scj_carp_rust::logging::{set_log_level, LogLevel};
+use scj_carp_rust::mbg::MBG;
+use scj_carp_rust::rearrangement::RearrangementGraph;
+use scj_carp_rust::scan::{scan_graph, scan_graph_enum_multithread, scan_graph_multiscale, ContextUnit, Flank};
+
+const DEPTHS : [usize;3] = [30,200,1000];
+
+fn main() {
+    set_log_level(LogLevel::Error);
+    let mut graphs = Vec::new();
+    for f in read_dir("testfiles/random/").expect("Could not read testfiles/random") {
+        let path = f.expect("Could not read directory entry").path();
+        let mut mbg = MBG::from_gfa(path.to_str().unwrap(), true).expect("Could not read gfa file");
+        mbg.fill_telomeres();
+        graphs.push(mbg);
+    }
+    println!("#Depth\tscan_graph\tscan_graph_enum_multithread\tSpeedup");
+    for depth in DEPTHS {
+        let mut reference = Duration::ZERO;
+        let mut workspace = Duration::ZERO;
+        for g in &graphs {
+            let start = Instant::now();
+            let expected = scan_graph(g, depth);
+            reference += start.elapsed();
+            let start = Instant::now();
+            let actual = scan_graph_enum_multithread(g, depth, 1);
+            workspace += start.elapsed();
+            assert_eq!(expected,actual,"Scan results differ at depth {depth}");
+        }
+        println!("{depth}\t{:.3}s\t{:.3}s\t{:.2}x",reference.as_secs_f64(),workspace.as_secs_f64(),
+            reference.as_secs_f64()/workspace.as_secs_f64());
+    }
+    //all context lengths from a single exploration per marker
+    let mut reference = Duration::ZERO;
+    let mut workspace = Duration::ZERO;
+    for g in &graphs {
+        let start = Instant::now();
+        let expected : Vec<_> = DEPTHS.iter().map(|depth| scan_graph(g, *depth)).collect();
+        reference += start.elapsed();
+        let start = Instant::now();
+        let actual = scan_graph_multiscale(g, &DEPTHS, ContextUnit::BasePairs, Flank::Both, 1);
+        workspace += start.elapsed();
+        for (k,depth) in DEPTHS.iter().enumerate() {
+            for (m,complexity) in &expected[k] {
+                assert_eq!(*complexity,actual[m][k].complexity,"Multiscale results differ at depth {depth}");
+            }
+        }
+    }
+    println!("all\t{:.3}s\t{:.3}s\t{:.2}x",reference.as_secs_f64(),workspace.as_secs_f64(),
+        reference.as_secs_f64()/workspace.as_secs_f64());
+}
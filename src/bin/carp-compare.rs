@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use clap::{arg, value_parser, ArgGroup, ArgMatches, Command};
+use scj_carp_rust::logging::set_verbosity;
+use scj_carp_rust::log_info;
+use scj_carp_rust::rearrangement::RearrangementGraph;
+use scj_carp_rust::util::*;
+use scj_carp_rust::mbg::MBG;
+use scj_carp_rust::scan::*;
+use scj_carp_rust::compare::*;
+
+fn load_scan(matches : &ArgMatches, side : &str, contextlen : usize, unit : ContextUnit, ignore_gfa_overlap : bool, n_threads : usize) -> HashMap<String,usize> {
+    if let Some(table) = matches.get_one::<String>(&format!("table-{side}")) {
+        log_info!("Reading scan table {table}...");
+        return read_scan_table(table).expect("Could not read scan table");
+    }
+    let gfa : &String = matches.get_one(&format!("gfa-{side}")).expect("CLI Parsing gone wrong");
+    log_info!("Reading graph {gfa}...");
+    let mut graph = MBG::from_gfa(gfa, ignore_gfa_overlap).expect("Something went wrong parsing input files");
+    graph.fill_telomeres();
+    log_info!("Scanning graph {gfa}...");
+    let node_c : HashMap<_,_> = scan_graph_multiscale(&graph, &[contextlen], unit, Flank::Both, n_threads).into_iter()
+        .map(|(m,ns)| (m,ns[0].complexity)).collect();
+    named_complexities(&graph, &node_c)
+}
+
+fn main() {
+    let matches = Command::new("carp-compare")
+        .arg(arg!(--"gfa-a" <f> "First graph as GFA file."))
+        .arg(arg!(--"table-a" <f> "First scan as table written by carp-scan --output-table."))
+        .group(ArgGroup::new("a").args(["gfa-a","table-a"]).required(true))
+        .arg(arg!(--"gfa-b" <f> "Second graph as GFA file."))
+        .arg(arg!(--"table-b" <f> "Second scan as table written by carp-scan --output-table."))
+        .group(ArgGroup::new("b").args(["gfa-b","table-b"]).required(true))
+        .arg(arg!(-c --"context-len" <c> "Context length used to scan graphs.").value_parser(value_parser!(usize)).default_value("500"))
+        .arg(arg!(--"context-unit" <u> "Unit of the context length: base pairs (bp) or number of nodes (nodes).").value_parser(["bp","nodes"]).default_value("bp"))
+        .arg(arg!(--"ignore-gfa-overlap").num_args(0))
+        .arg(arg!(-t --"num-threads" <t> "Number of threads to use in the scanning phase.").value_parser(value_parser!(usize)).default_value("1"))
+        .arg(arg!(--"output-table" <f> "Output the complexities of all nodes in both scans with their differences."))
+        .arg(arg!(-q --"quiet" "Only log errors to standard error.").num_args(0))
+        .arg(arg!(-v --"verbose" "Log debugging information to standard error.").num_args(0))
+        .group(ArgGroup::new("verbosity").args(["quiet","verbose"]))
+        .get_matches();
+    set_verbosity(matches.get_flag("quiet"), matches.get_flag("verbose"));
+    let contextlen = *matches.get_one("context-len").expect("CLI Parsing gone wrong");
+    let n_threads = *matches.get_one("num-threads").expect("CLI Parsing gone wrong");
+    let ignore_gfa_overlap = matches.get_flag("ignore-gfa-overlap");
+    let unit = match matches.get_one::<String>("context-unit").map(|u| u.as_str()) {
+        Some("nodes") => ContextUnit::Nodes,
+        _ => ContextUnit::BasePairs
+    };
+    log_info!("{}",CARP_LOGO);
+    let left = load_scan(&matches, "a", contextlen, unit, ignore_gfa_overlap, n_threads);
+    let right = load_scan(&matches, "b", contextlen, unit, ignore_gfa_overlap, n_threads);
+    let cmp = compare_scans(&left, &right);
+    write_comparison_summary(&cmp, &mut io::stdout()).expect("Could not write summary");
+    if let Some(p) = matches.get_one::<String>("output-table") {
+        let mut fl = File::create(p).expect("Could not create table file.");
+        write_comparison_table(&cmp, &left, &right, &mut fl).expect("Could not write table file.");
+    }
+}
@@ -1,9 +1,11 @@
 use clap::{arg, value_parser, ArgGroup, Command};
 use std::io;
 use std::process::exit;
+use scj_carp_rust::logging::set_verbosity;
+use scj_carp_rust::{log_error, log_info, log_warn};
 use scj_carp_rust::mbg::MBG;
 use scj_carp_rust::rearrangement::RearrangementGraph;
-use scj_carp_rust::scan::adjacency_neighborhood;
+use scj_carp_rust::scan::{adjacency_neighborhood_reach, ContextUnit, Flank};
 use scj_carp_rust::gfa::partial2gfa;
 use scj_carp_rust::measure::carp_measure_from_adjacencies;
 fn main() {
@@ -17,23 +19,28 @@ fn main() {
                     .required(true))
         .arg(arg!(-n --"start-node"<n> "start extracting from node"))
         .arg(arg!(-d --"max-dist" <d> "Maximum distance from start node").value_parser(value_parser!(usize)).required(true))
+        .arg(arg!(--"context-unit" <u> "Unit of the maximum distance: base pairs (bp) or number of nodes (nodes).").value_parser(["bp","nodes"]).default_value("bp"))
         .arg(arg!(--"ignore-gfa-overlap").num_args(0))
         .group(ArgGroup::new("overlap").args(["ignore-gfa-overlap","size-thresh"]))
+        .arg(arg!(-q --"quiet" "Only log errors to standard error.").num_args(0))
+        .arg(arg!(-v --"verbose" "Log debugging information to standard error.").num_args(0))
+        .group(ArgGroup::new("verbosity").args(["quiet","verbose"]))
         .get_matches();
+    set_verbosity(matches.get_flag("quiet"), matches.get_flag("verbose"));
     let ignore_gfa_overlap = matches.get_flag(&"ignore-gfa-overlap");
     let is_gfa = matches.get_one::<String>("gfa").is_some();
     let is_unimog = matches.get_one::<String>("unimog").is_some();
     let mut thresh = *matches.get_one(&"size-thresh").expect("CLI Parsing gone wrong");
 
     if !is_gfa && ignore_gfa_overlap {
-        eprintln!("Warning: Not a gfa file. Ignoring --ignore-gfa-overlap flag.")
+        log_warn!("Warning: Not a gfa file. Ignoring --ignore-gfa-overlap flag.");
     }
     if is_unimog && thresh > 0 {
-        eprintln!("Warning: Unimog files do not support node sizes. Ignoring --size-thresh flag.");
+        log_warn!("Warning: Unimog files do not support node sizes. Ignoring --size-thresh flag.");
         thresh = 0;
     }
     if thresh > 0 && !ignore_gfa_overlap {
-        eprintln!("Error: A gfa graph can only be trimmed with the --ignore-gfa-overlap flag.");
+        log_error!("Error: A gfa graph can only be trimmed with the --ignore-gfa-overlap flag.");
         exit(1);
     }
     let maybe_graph = match (matches.get_one::<String>("gfa")
@@ -44,17 +51,22 @@ fn main() {
     };
     
     let mut graph = maybe_graph.expect("Something went wrong parsing input files");
-    eprintln!("Adding telomeres to complete graph.");
+    log_info!("Adding telomeres to complete graph.");
     graph.fill_telomeres();
     if thresh > 0 {
-        eprintln!("Trimming graph.");
+        log_info!("Trimming graph.");
         graph.trim_singlethread(thresh);
     }
     graph.fill_telomeres();
     let start_node : &String = matches.get_one(&"start-node").expect("CLI Parsing gone wrong");
     let max_dist : usize = *matches.get_one(&"max-dist").expect("CLI Parsing gone wrong");
+    let unit = match matches.get_one::<String>("context-unit").map(|u| u.as_str()) {
+        Some("nodes") => ContextUnit::Nodes,
+        _ => ContextUnit::BasePairs
+    };
     let marker = graph.name_to_marker(&start_node).expect("Given node is not part of the (trimmed) graph. Make sure that this node id exists and try a lower size threshold.");
-    let adjacencies = adjacency_neighborhood(marker, max_dist, &graph);
+    let adjacencies = adjacency_neighborhood_reach(marker, max_dist, unit, Flank::Both, &graph).into_keys().collect();
+    //the gfa of the neighborhood goes to standard output, so the measure is printed to standard error regardless of --quiet
     eprintln!("{}",carp_measure_from_adjacencies(&adjacencies));
     partial2gfa(&graph, &adjacencies);
 }
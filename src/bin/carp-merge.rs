@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::exit;
+use clap::{arg, value_parser, ArgGroup, ArgMatches, Command};
+use scj_carp_rust::logging::set_verbosity;
+use scj_carp_rust::{log_error, log_info, log_warn};
+use scj_carp_rust::rearrangement::*;
+use scj_carp_rust::util::*;
+use scj_carp_rust::mbg::MBG;
+use scj_carp_rust::scan::*;
+use scj_carp_rust::output::*;
+use scj_carp_rust::color::*;
+use scj_carp_rust::histogram::*;
+
+/// Histogram outputs requested on the command line.
+fn histogram_params(matches : &ArgMatches) -> HistogramParams {
+    HistogramParams {
+        table : matches.get_one::<String>("output-histogram").cloned(),
+        svg : matches.get_one::<String>("histogram-svg").cloned(),
+        n_buckets : matches.get_one::<usize>("histogram-buckets").copied(),
+        log_x : matches.get_flag("histogram-log-x"),
+        log_y : matches.get_flag("histogram-log-y"),
+        percentiles : matches.get_one::<f64>("lower-percentile").copied().zip(matches.get_one::<f64>("higher-percentile").copied()),
+        ties : *matches.get_one::<TiePolicy>("ties").expect("CLI parsing gone wrong")
+    }
+}
+
+fn main() {
+    let matches = Command::new("carp-merge")
+        .arg(arg!(<shards> ... "Tables written by carp-scan --shard i/n --output-table."))
+        .arg(arg!(-s --"size-thresh" <st> "Size threshold for nodes, must be the same as for the shards.")
+        .value_parser(value_parser!(usize))
+        .default_value("0"))
+        .arg(arg!(-g --"gfa" <f> "Graph the shards were scanned on as GFA file."))
+        .arg(arg!(-u --"unimog" <f> "Graph the shards were scanned on as unimog file."))
+        .group(ArgGroup::new("infile").args(["gfa","unimog"])
+                    .required(true))
+        .arg(arg!(--"colored-gfa" <f> "Output annotated gfa with complexities."))
+        .arg(arg!(--"bandage-csv" <f> "Output colors and complexities as CSV that can be loaded in Bandage together with the original gfa."))
+        .arg(arg!(--"annotated-gfa" <f> "Output the original gfa with the color and complexity tags of the colored gfa added to its segments.").requires("gfa"))
+        .arg(arg!(--"color-scale" <s> "Scale to map complexities to colors: linear, log or rank.").value_parser(["linear","log","rank"]).default_value("log"))
+        .arg(arg!(--"palette" <p> "Colors of the colored gfa: heat, viridis, grayscale or bluered.").value_parser(["heat","viridis","grayscale","bluered"]).default_value("heat"))
+        .arg(arg!(--"color-min" <c> "Complexity mapped to the lowest color, e.g. to color several graphs alike. Default: lowest complexity.").value_parser(value_parser!(usize)))
+        .arg(arg!(--"color-max" <c> "Complexity mapped to the highest color. Default: highest complexity.").value_parser(value_parser!(usize)))
+        .arg(arg!(--"output-histogram" <f> "Output a histogram of complexities."))
+        .arg(arg!(--"histogram-svg" <f> "Plot the histogram of complexities as SVG, marking the percentiles selected with --lower-percentile/--higher-percentile."))
+        .arg(arg!(--"histogram-buckets" <n> "Group the complexities of the histogram into the given number of buckets.").value_parser(value_parser!(usize)))
+        .arg(arg!(--"histogram-log-x" "Use buckets of exponentially growing width.").num_args(0).requires("histogram-buckets"))
+        .arg(arg!(--"histogram-log-y" "Plot the number of nodes on a logarithmic scale.").num_args(0))
+        .arg(arg!(--"output-table" <f> "Output the merged scan results of all nodes sorted by node name."))
+        .arg(arg!(--"lower-percentile" <lo> "Output nodes that lie between the lower and higher percentile to standard output.").value_parser(value_parser!(f64)))
+        .arg(arg!(--"higher-percentile" <hi> "Output nodes that lie between the lower and higher percentile to standard output.").value_parser(value_parser!(f64)).default_value("1.00"))
+        .arg(arg!(--ties <policy> "Selection of nodes with equal complexity at the bounds of the percentile range: lowest-rank, include, exclude or exact.").value_parser(TiePolicy::parse).default_value("lowest-rank"))
+        .arg(arg!(-t --"num-threads" <t> "Number of threads to use for trimming.").value_parser(value_parser!(usize)).default_value("1"))
+        .arg(arg!(--"ignore-gfa-overlap").num_args(0))
+        .arg(arg!(-q --"quiet" "Only log errors to standard error.").num_args(0))
+        .arg(arg!(-v --"verbose" "Log debugging information to standard error.").num_args(0))
+        .group(ArgGroup::new("verbosity").args(["quiet","verbose"]))
+        .get_matches();
+    set_verbosity(matches.get_flag("quiet"), matches.get_flag("verbose"));
+    let thresh : usize = *matches.get_one("size-thresh").expect("CLI Parsing gone wrong");
+    let n_threads = *matches.get_one("num-threads").expect("CLI Parsing gone wrong");
+    let ignore_gfa_overlap = matches.get_flag("ignore-gfa-overlap");
+    if thresh > 0 && !ignore_gfa_overlap {
+        log_error!("Error: A gfa graph can only be trimmed with the --ignore-gfa-overlap flag.");
+        exit(1);
+    }
+    log_info!("{}",CARP_LOGO);
+    log_info!("Reading graph...");
+    let maybe_graph = match (matches.get_one::<String>("gfa")
+            , matches.get_one::<String>("unimog")) {
+        (Some(gfaf),_) => MBG::from_gfa(gfaf,ignore_gfa_overlap),
+        (_,Some(unimog)) =>  MBG::from_unimog(unimog),
+        (_,_) => Err(io::Error::other("No file specified."))
+    };
+    let mut graph = maybe_graph.expect("Something went wrong parsing input files");
+    graph.fill_telomeres();
+    if thresh > 0 {
+        log_info!("Trimming graph.");
+        graph.trim_any(thresh,n_threads);
+        graph.fill_telomeres();
+    }
+    let mut results : Option<ScanResults> = None;
+    for path in matches.get_many::<String>("shards").expect("CLI Parsing gone wrong") {
+        log_info!("Reading shard {path}...");
+        let shard = read_scan_results(&graph, path).expect("Could not read shard table");
+        match &mut results {
+            None => results = Some(shard),
+            Some(r) => r.merge(shard).expect("Could not merge shard")
+        }
+    }
+    let results = results.expect("CLI Parsing gone wrong");
+    let node_c = results.node_complexities();
+    let missing = scannable_markers(&graph).into_iter().filter(|m| !node_c.contains_key(m)).count();
+    if missing > 0 {
+        log_warn!("Warning: {missing} nodes of the graph are not part of any shard.");
+    }
+    if node_c.is_empty() {
+        log_error!("Error: The shards contain no nodes.");
+        exit(1);
+    }
+    let color_params = ColorMapParams {
+        scale : matches.get_one::<String>("color-scale").and_then(|s| ColorScale::from_name(s)).expect("CLI parsing gone wrong"),
+        palette : matches.get_one::<String>("palette").and_then(|p| Palette::from_name(p)).expect("CLI parsing gone wrong"),
+        min : matches.get_one("color-min").copied(),
+        max : matches.get_one("color-max").copied()
+    };
+    let colors = color_params.fit(&node_c);
+    if let Some(colorgfapath) = matches.get_one::<String>("colored-gfa") {
+        write_colored_gfa(&graph, &results, &colors, colorgfapath).expect("Could not write colored gfa file");
+    }
+    if let Some(csvpath) = matches.get_one::<String>("bandage-csv") {
+        let mut fl = BufWriter::new(File::create(csvpath).expect("Could not create Bandage CSV file."));
+        write_bandage_csv(&graph, &results, &colors, &mut fl).expect("Could not write Bandage CSV file.");
+    }
+    if let (Some(annotatedpath),Some(gfaf)) = (matches.get_one::<String>("annotated-gfa"),matches.get_one::<String>("gfa")) {
+        let mut fl = BufWriter::new(File::create(annotatedpath).expect("Could not create annotated gfa file."));
+        let annotated = annotate_gfa(&graph, &results.annotations(&colors), gfaf, &mut fl).expect("Could not write annotated gfa file.");
+        fl.flush().expect("Could not write annotated gfa file.");
+        log_info!("Annotated {annotated} segments.");
+    }
+    write_histograms(&histogram(&node_c), &histogram_params(&matches)).expect("Could not write histogram files.");
+    if let Some(tablepath) = matches.get_one::<String>("output-table") {
+        let mut fl = File::create(tablepath).expect("Could not create table file.");
+        results.write_table(&graph, &mut fl).expect("Could not write table file.");
+    }
+    if let (Some(lo),Some(hi)) = (matches.get_one::<f64>("lower-percentile"),matches.get_one::<f64>("higher-percentile")) {
+        results.write_percentiles(&graph, *lo, *hi, *matches.get_one::<TiePolicy>("ties").expect("CLI parsing gone wrong"), &mut io::stdout()).expect("Could not write percentiles.");
+    }
+}
@@ -1,68 +1,58 @@
-use std::collections::{HashMap};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, BufWriter, Write};
 use std::process::exit;
-use clap::{arg, value_parser, ArgGroup, Command};
+use clap::{arg, value_parser, ArgGroup, ArgMatches, Command};
+use scj_carp_rust::logging::set_verbosity;
+use scj_carp_rust::{log_error, log_info, log_warn};
 use scj_carp_rust::rearrangement::*;
 use scj_carp_rust::util::*;
 use scj_carp_rust::mbg::MBG;
 use scj_carp_rust::scan::*;
+use scj_carp_rust::sample::*;
+use scj_carp_rust::output::*;
+use scj_carp_rust::color::*;
+use scj_carp_rust::histogram::*;
+use scj_carp_rust::checkpoint::*;
+use scj_carp_rust::hotspot::*;
+use scj_carp_rust::gfa::reference_paths_gfa;
+use scj_carp_rust::reference::*;
+use scj_carp_rust::significance::*;
 
-fn to_gfa_annotated(graph : &impl RearrangementGraph, annotations : &HashMap<Marker,String>, file : &str) -> std::io::Result<()>
-{
-    let mut file = File::create(file)?;
-    let mnames = graph.marker_names();
-    for (mid,mname) in &mnames {
-        file.write(format!("S\t{mname}\t*").as_bytes())?;
-        if let Some(msiz) = graph.node_size(*mid) {
-            file.write(format!("\tLN:i:{msiz}").as_bytes())?;
-        }
-        if let Some(t) = annotations.get(mid) {
-            file.write(format!("\t{t}").as_bytes())?;
-        }
-        file.write("\n".as_bytes())?;
-    }
-    for (x,y) in graph.iter_adjacencies() {
-        let m1n = marker(x);
-        let m2n = marker(y);
-        if let Some(m1) = mnames.get(&m1n) {
-            if let Some(m2) = mnames.get(&m2n) {
-                let orient1 = if is_tail(x) {
-                    "-"
-                } else {
-                    "+"
-                };
-                let orient2 = if is_tail(y) {
-                    "+"
-                } else {
-                    "-"
-                };
-
-                file.write(format!("L\t{m1}\t{orient1}\t{m2}\t{orient2}\t0M\n").as_bytes())?;
-            }
-        }
+/// Parses a shard given as `i/n` with `0 <= i < n`.
+fn parse_shard(s : &str) -> Result<(usize,usize),String> {
+    let (i,n) = s.split_once('/').ok_or(String::from("expected format i/n"))?;
+    let i : usize = i.parse().map_err(|_| format!("invalid shard index {i}"))?;
+    let n : usize = n.parse().map_err(|_| format!("invalid number of shards {n}"))?;
+    if i >= n {
+        return Err(format!("shard index must be smaller than the number of shards {n}"));
     }
-    Ok(())
+    Ok((i,n))
 }
 
-fn to_heat_html( x :f64) -> String {
-
-    let redval = (255.0*x.min(0.5)*2.0) as u8;
-    let greenval = (255.0*(x-0.5).max(0.0)*2.0) as u8;
-    let blueval = 110 as u8;
-    format!("#{:02x}{:02x}{:02x}",redval,greenval,blueval)
+/// Scans `flank` of the given markers or, if no markers are given, of all markers, writing the results to the checkpoint if there is one.
+fn scan_flank(graph : &MBG, contextlens : &[usize], unit : ContextUnit, flank : Flank, subset : &Option<Vec<Marker>>, checkpoint : &mut Option<Checkpoint>, n_threads : usize) -> HashMap<Marker,Vec<NeighborhoodStats>> {
+    match (subset,checkpoint) {
+        (Some(markers),Some(cp)) => cp.scan(graph, flank, markers, n_threads).expect("Could not write checkpoint"),
+        (None,Some(cp)) => cp.scan(graph, flank, &scannable_markers(graph), n_threads).expect("Could not write checkpoint"),
+        (Some(markers),None) => scan_markers_multithread(graph, contextlens, unit, flank, markers, n_threads),
+        (None,None) => scan_graph_multiscale(graph, contextlens, unit, flank, n_threads)
+    }
 }
 
-fn write_hist(hist:&HashMap<usize,usize>,path : &str) -> std::io::Result<()> {
-    let mut file = File::create(path)?;
-    for (a,b) in hist {
-        write!(&mut file, "{a}\t{b}\n")?;
+/// Histogram outputs requested on the command line.
+fn histogram_params(matches : &ArgMatches) -> HistogramParams {
+    HistogramParams {
+        table : matches.get_one::<String>("output-histogram").cloned(),
+        svg : matches.get_one::<String>("histogram-svg").cloned(),
+        n_buckets : matches.get_one::<usize>("histogram-buckets").copied(),
+        log_x : matches.get_flag("histogram-log-x"),
+        log_y : matches.get_flag("histogram-log-y"),
+        percentiles : matches.get_one::<f64>("lower-percentile").copied().zip(matches.get_one::<f64>("higher-percentile").copied()),
+        ties : *matches.get_one::<TiePolicy>("ties").expect("CLI parsing gone wrong")
     }
-    Ok(())
 }
 
-
-
 fn main() {
     //TODO: make struct
     let cmd = Command::new("scj-carp")
@@ -73,34 +63,79 @@ fn main() {
         .arg(arg!(-u --"unimog" <f> "Specify input as unimog file."))
         .group(ArgGroup::new("infile").args(["gfa","unimog"])
                     .required(true))
-        .arg(arg!(-c --"context-len" <c>).value_parser(value_parser!(usize)).default_value("500"))
+        .arg(arg!(-c --"context-len" <c> "Comma separated context lengths. Colors, histogram and percentiles refer to the first one.").value_parser(value_parser!(usize)).value_delimiter(',').default_value("500"))
+        .arg(arg!(--"context-unit" <u> "Unit of the context length: base pairs (bp) or number of nodes (nodes).").value_parser(["bp","nodes"]).default_value("bp"))
+        .arg(arg!(--"flanks" "Additionally output the complexities of the neighborhoods reached via only the head and only the tail of each node.").num_args(0))
+        .arg(arg!(--"nodes" <f> "Only scan the nodes whose names are listed (one per line) in the given file.").conflicts_with("sampling"))
+        .arg(arg!(--"shard" <s> "Only scan shard i/n (0-based) of the nodes and write the results to the output table, see carp-merge.").value_parser(parse_shard).conflicts_with("sampling").requires("output-table"))
+        .arg(arg!(--"checkpoint" <f> "Regularly save the scanned nodes to the given file, so that an interrupted scan can be resumed.").conflicts_with("sampling"))
+        .arg(arg!(--"checkpoint-interval" <n> "Number of nodes scanned between two saves of the checkpoint [default: 10000].").value_parser(value_parser!(usize)))
+        .arg(arg!(--"resume" "Skip the nodes saved in the checkpoint file and continue the scan.").num_args(0).requires("checkpoint"))
+        .arg(arg!(--"stream" "Write the results of each node to the output table as soon as it is scanned instead of keeping all results in memory.").num_args(0)
+            .requires("output-table").conflicts_with_all(["sampling","flanks","checkpoint","colored-gfa","bandage-csv","annotated-gfa","hotspots","reference","significance"]))
         .arg(arg!(--"colored-gfa" <f> "Output annotated gfa with complexities."))
+        .arg(arg!(--"bandage-csv" <f> "Output colors and complexities as CSV that can be loaded in Bandage together with the original gfa."))
+        .arg(arg!(--"annotated-gfa" <f> "Output the original gfa with the color and complexity tags of the colored gfa added to its segments.").requires("gfa"))
+        .arg(arg!(--"color-scale" <s> "Scale to map complexities to colors: linear, log or rank.").value_parser(["linear","log","rank"]).default_value("log"))
+        .arg(arg!(--"palette" <p> "Colors of the colored gfa: heat, viridis, grayscale or bluered.").value_parser(["heat","viridis","grayscale","bluered"]).default_value("heat"))
+        .arg(arg!(--"color-min" <c> "Complexity mapped to the lowest color, e.g. to color several graphs alike. Default: lowest complexity.").value_parser(value_parser!(usize)))
+        .arg(arg!(--"color-max" <c> "Complexity mapped to the highest color. Default: highest complexity.").value_parser(value_parser!(usize)))
         .arg(arg!(--"output-histogram" <f> "Output a histogram of complexities."))
+        .arg(arg!(--"histogram-svg" <f> "Plot the histogram of complexities as SVG, marking the percentiles selected with --lower-percentile/--higher-percentile."))
+        .arg(arg!(--"histogram-buckets" <n> "Group the complexities of the histogram into the given number of buckets.").value_parser(value_parser!(usize)))
+        .arg(arg!(--"histogram-log-x" "Use buckets of exponentially growing width.").num_args(0).requires("histogram-buckets"))
+        .arg(arg!(--"histogram-log-y" "Plot the number of nodes on a logarithmic scale.").num_args(0))
+        .arg(arg!(--"output-table" <f> "Output the scan results of all nodes sorted by node name."))
+        .arg(arg!(--"hotspots" <f> "Output regions of high complexity nodes (hotspots) to the given file."))
+        .arg(arg!(--"hotspot-percentile" <p> "Nodes whose complexity lies at or above this percentile are part of hotspots.").value_parser(value_parser!(f64)).default_value("0.99"))
+        .arg(arg!(--"hotspot-complexity" <c> "Nodes with at least this complexity are part of hotspots (instead of --hotspot-percentile).").value_parser(value_parser!(usize)))
+        .arg(arg!(--"hotspot-gap" <d> "Maximum distance (in the context unit) between high complexity nodes of the same hotspot.").value_parser(value_parser!(usize)).default_value("0"))
+        .arg(arg!(--"reference" <r> "Path name, PanSN sample or walk sample of the reference to project complexities onto.").requires("gfa"))
+        .arg(arg!(--"bedgraph" <f> "Output the complexities of the segments on the reference as bedGraph.").requires("reference"))
+        .arg(arg!(--"hotspot-bed" <f> "Output the reference intervals of hotspots as BED.").requires("reference"))
+        .arg(arg!(--"significance" <f> "Output empirical p-values and z-scores of the complexities against randomized graphs to the given file.").conflicts_with("sampling"))
+        .arg(arg!(--"randomizations" <n> "Number of randomized graphs to compare against.").value_parser(value_parser!(usize)).default_value("20"))
+        .arg(arg!(--"swaps" <k> "Number of adjacency swaps per adjacency to randomize a graph.").value_parser(value_parser!(usize)).default_value("10"))
         .arg(arg!(--"lower-percentile" <lo> "Output nodes that lie between the lower and higher percentile to standard output.").value_parser(value_parser!(f64)))
         .arg(arg!(--"higher-percentile" <hi> "Output nodes that lie between the lower and higher percentile to standard output.").value_parser(value_parser!(f64)).default_value("1.00"))
+        .arg(arg!(--ties <policy> "Selection of nodes with equal complexity at the bounds of the percentile range: lowest-rank, include, exclude or exact.").value_parser(TiePolicy::parse).default_value("lowest-rank"))
         .arg(arg!(-t --"num-threads" <t> "Number of threads to use in the scanning phase. Default: 1.").value_parser(value_parser!(usize)).default_value("1"))
-        .arg(arg!(--"ignore-gfa-overlap").num_args(0));
+        .arg(arg!(--"ignore-gfa-overlap").num_args(0))
+        .arg(arg!(-q --"quiet" "Only log errors to standard error.").num_args(0))
+        .arg(arg!(-v --"verbose" "Log debugging information to standard error.").num_args(0))
+        .group(ArgGroup::new("verbosity").args(["quiet","verbose"]))
+        .arg(arg!(--"sample-fraction" <f> "Only scan the given fraction of markers and assign each other marker the complexity of its nearest sampled marker.").value_parser(value_parser!(f64)))
+        .arg(arg!(--"sample-count" <n> "Only scan the given number of markers and assign each other marker the complexity of its nearest sampled marker.").value_parser(value_parser!(usize)))
+        .group(ArgGroup::new("sampling").args(["sample-fraction","sample-count"]))
+        .arg(arg!(--"sample-weighting" <w> "How to sample markers: uniform or weighted by length.").value_parser(["uniform","length"]).default_value("uniform").requires("sampling"))
+        .arg(arg!(--"seed" <s> "Seed for sampling markers and randomizing graphs.").value_parser(value_parser!(u64)).default_value("0"))
+        .arg(arg!(--"write-sampling" <p> "Path to write the sampling parameters and statistics and the anchor of each node to.").requires("sampling"));
     
     let matches = cmd.get_matches();
+    set_verbosity(matches.get_flag("quiet"), matches.get_flag("verbose"));
     let mut thresh = *matches.get_one(&"size-thresh").expect("CLI Parsing gone wrong");
-    let contextlen = *matches.get_one(&"context-len").expect("CLI Parsing gone wrong");
+    let contextlens : Vec<usize> = matches.get_many(&"context-len").expect("CLI Parsing gone wrong").copied().collect();
+    let unit = match matches.get_one::<String>("context-unit").map(|u| u.as_str()) {
+        Some("nodes") => ContextUnit::Nodes,
+        _ => ContextUnit::BasePairs
+    };
     let n_threads = *matches.get_one(&"num-threads").expect("CLI parsing gone wrong");
     let ignore_gfa_overlap = matches.get_flag(&"ignore-gfa-overlap");
     let is_gfa = matches.get_one::<String>("gfa").is_some();
     let is_unimog = matches.get_one::<String>("unimog").is_some();
     if !is_gfa && ignore_gfa_overlap {
-        eprintln!("Warning: Not a gfa file. Ignoring --ignore-gfa-overlap flag.")
+        log_warn!("Warning: Not a gfa file. Ignoring --ignore-gfa-overlap flag.");
     }
     if is_unimog && thresh > 0 {
-        eprintln!("Warning: Unimog files do not support node sizes. Ignoring --size-thresh flag.");
+        log_warn!("Warning: Unimog files do not support node sizes. Ignoring --size-thresh flag.");
         thresh = 0;
     }
     if thresh > 0 && !ignore_gfa_overlap {
-        eprintln!("Error: A gfa graph can only be trimmed with the --ignore-gfa-overlap flag.");
+        log_error!("Error: A gfa graph can only be trimmed with the --ignore-gfa-overlap flag.");
         exit(1);
     }
-    eprintln!("{}",CARP_LOGO);
-    eprintln!("Reading graph...");
+    log_info!("{}",CARP_LOGO);
+    log_info!("Reading graph...");
     let maybe_graph = match (matches.get_one::<String>("gfa")
             , matches.get_one::<String>("unimog")) {
         (Some(gfaf),_) => MBG::from_gfa(gfaf,ignore_gfa_overlap),
@@ -108,42 +143,164 @@ fn main() {
         (_,_) => Err(io::Error::new(io::ErrorKind::Other,"No file specified."))
     };
     let mut graph = maybe_graph.expect("Something went wrong parsing input files");
-    eprintln!("Adding telomeres to complete graph.");
+    log_info!("Adding telomeres to complete graph.");
     graph.fill_telomeres();
     if thresh > 0 {
-        eprintln!("Trimming graph.");
+        log_info!("Trimming graph.");
         graph.trim_any(thresh,n_threads);
         graph.fill_telomeres();
     }
-    let node_c  =  scan_graph_enum_multithread(&graph, contextlen,n_threads);
-    let mn = *node_c.values().min().unwrap();
-    let mut mx = * node_c.values().max().unwrap();
-    if mx == 0 {
-        mx =  1;
+    let size = match (matches.get_one::<f64>("sample-fraction"),matches.get_one::<usize>("sample-count")) {
+        (Some(f),_) => Some(SampleSize::Fraction(*f)),
+        (_,Some(n)) => Some(SampleSize::Count(*n)),
+        (_,_) => None
+    };
+    let subset = matches.get_one::<String>("nodes").map(|p| {
+        let names = read_node_names(p).expect("Could not read node file");
+        let (markers,unknown) = resolve_node_names(&graph, &names);
+        for name in &unknown {
+            log_warn!("Warning: Node {name} is not part of the (trimmed) graph. Skipping it.");
+        }
+        log_info!("Scanning {} of {} listed nodes.",markers.len(),names.len());
+        markers
+    });
+    let subset = match (subset,matches.get_one::<(usize,usize)>("shard")) {
+        (subset,Some((i,n))) => {
+            let markers = shard_markers(&subset.unwrap_or_else(|| scannable_markers(&graph)), *i, *n);
+            log_info!("Scanning {} nodes of shard {i}/{n}.",markers.len());
+            Some(markers)
+        },
+        (subset,None) => subset
+    };
+    if matches.get_flag("stream") {
+        let tablepath : &String = matches.get_one("output-table").expect("CLI parsing gone wrong");
+        let markers = subset.unwrap_or_else(|| scannable_markers(&graph));
+        let fl = BufWriter::new(File::create(tablepath).expect("Could not create table file."));
+        let mut table = TableStream::new(&graph, &contextlens, unit, fl).expect("Could not write table file.");
+        scan_markers_streaming(&graph, &contextlens, unit, Flank::Both, &markers, n_threads, |m,stats| table.write(&graph, m, &stats))
+            .expect("Could not write table file.");
+        table.finish().expect("Could not write table file.");
+        write_histograms(&table_histogram(tablepath).expect("Could not read table file."), &histogram_params(&matches)).expect("Could not write histogram files.");
+        if let (Some(lo),Some(hi)) = (matches.get_one::<f64>("lower-percentile"),matches.get_one::<f64>("higher-percentile")) {
+            write_table_percentiles(tablepath, *lo, *hi, *matches.get_one::<TiePolicy>("ties").expect("CLI parsing gone wrong"), &mut io::stdout()).expect("Could not write percentiles.");
+        }
+        return;
+    }
+    let mut checkpoint = matches.get_one::<String>("checkpoint").map(|p| {
+        let interval = matches.get_one("checkpoint-interval").copied().unwrap_or(CHECKPOINT_INTERVAL);
+        if matches.get_flag("resume") {
+            log_info!("Resuming from checkpoint {p}.");
+            Checkpoint::resume(p, &graph, &contextlens, unit, interval).expect("Could not resume from checkpoint")
+        } else {
+            Checkpoint::create(p, &contextlens, unit, interval).expect("Could not create checkpoint")
+        }
+    });
+    let (scales,sampled) = if let (None,Some(size)) = (&subset,size) {
+        let weighting = match matches.get_one::<String>("sample-weighting").map(|w| w.as_str()) {
+            Some("length") => SampleWeighting::Length,
+            _ => SampleWeighting::Uniform
+        };
+        let params = SamplingParams { size, unit, weighting, seed : *matches.get_one("seed").expect("CLI parsing gone wrong") };
+        let sampled = sample_scan(&graph, &contextlens, &params, n_threads);
+        if let Some(p) = matches.get_one::<String>("write-sampling") {
+            let mut fl = File::create(p).expect("Could not create sampling report file.");
+            write_sampling_report(&graph, &params, &contextlens, &sampled, &mut fl).expect("Could not write sampling report.");
+        }
+        (sampled.neighborhoods.clone(),Some(sampled))
+    } else {
+        (scan_flank(&graph, &contextlens, unit, Flank::Both, &subset, &mut checkpoint, n_threads),None)
+    };
+    let mut results = ScanResults::new(&contextlens, unit, scales);
+    if matches.get_flag("flanks") {
+        for flank in [Flank::Head,Flank::Tail] {
+            log_info!("Scanning {} flanks.",flank.name());
+            let neighborhoods = match &sampled {
+                Some(s) => s.rescan(&graph, &contextlens, unit, flank, n_threads),
+                None => scan_flank(&graph, &contextlens, unit, flank, &subset, &mut checkpoint, n_threads)
+            };
+            results.scans.push((flank,neighborhoods));
+        }
     }
-    let mut colors = HashMap::new();
-    for (marker,carpi) in &node_c {
-        colors.insert(*marker, format!("CL:z:{}\tcrp:i:{}",to_heat_html(((*carpi-mn) as f64).log2()/(mx as f64).log2()),carpi));
+    let node_c = results.node_complexities();
+    if node_c.is_empty() && matches.get_one::<(usize,usize)>("shard").is_none() {
+        log_error!("Error: None of the nodes to scan is part of the (trimmed) graph.");
+        exit(1);
     }
     //find median node
     //node_c.sort_by(|a,b| a.1.cmp(&b.1));
     //let (node,complexity) = node_c.last().unwrap();
     //let backmap  = graph.marker_names();
     //println!("{} {}",backmap.get(&node).unwrap(),complexity)
+    let color_params = ColorMapParams {
+        scale : matches.get_one::<String>("color-scale").and_then(|s| ColorScale::from_name(s)).expect("CLI parsing gone wrong"),
+        palette : matches.get_one::<String>("palette").and_then(|p| Palette::from_name(p)).expect("CLI parsing gone wrong"),
+        min : matches.get_one("color-min").copied(),
+        max : matches.get_one("color-max").copied()
+    };
+    let colors = color_params.fit(&node_c);
     if let Some(colorgfapath) = matches.get_one::<String>("colored-gfa") {
-        to_gfa_annotated(&graph, &colors, &colorgfapath).expect("Could not write colored gfa file");
+        write_colored_gfa(&graph, &results, &colors, colorgfapath).expect("Could not write colored gfa file");
     }
-    if let Some(histogrampath) = matches.get_one::<String>("output-histogram") {
-        let hist = histogram(&node_c);
-        write_hist(&hist,histogrampath).expect("Could not write histogram file.");
+    if let Some(csvpath) = matches.get_one::<String>("bandage-csv") {
+        let mut fl = BufWriter::new(File::create(csvpath).expect("Could not create Bandage CSV file."));
+        write_bandage_csv(&graph, &results, &colors, &mut fl).expect("Could not write Bandage CSV file.");
     }
-    if let (Some(lo),Some(hi)) = (matches.get_one::<f64>("lower-percentile"),matches.get_one::<f64>("higher-percentile")) {
-        let mmap = graph.marker_names();
-        println!("#Node\tSCJ-CARP-measure in env");
-        for marker in top_percentile(&node_c, *lo, *hi) {
-            let complexity = node_c.get(&marker).unwrap();
-            let markerstring = mmap.get(&marker).unwrap();
-            println!("{markerstring}\t{complexity}");
+    if let (Some(annotatedpath),Some(gfaf)) = (matches.get_one::<String>("annotated-gfa"),matches.get_one::<String>("gfa")) {
+        let mut fl = BufWriter::new(File::create(annotatedpath).expect("Could not create annotated gfa file."));
+        let annotated = annotate_gfa(&graph, &results.annotations(&colors), gfaf, &mut fl).expect("Could not write annotated gfa file.");
+        fl.flush().expect("Could not write annotated gfa file.");
+        log_info!("Annotated {annotated} segments.");
+    }
+    write_histograms(&histogram(&node_c), &histogram_params(&matches)).expect("Could not write histogram files.");
+    let hotspot_bed = matches.get_one::<String>("hotspot-bed");
+    let max_gap = *matches.get_one("hotspot-gap").expect("CLI parsing gone wrong");
+    let hotspots = (matches.contains_id("hotspots") || hotspot_bed.is_some()).then(|| {
+        let cutoff = match matches.get_one::<usize>("hotspot-complexity") {
+            Some(c) => HotspotCutoff::Complexity(*c),
+            None => HotspotCutoff::Percentile(*matches.get_one("hotspot-percentile").expect("CLI parsing gone wrong"),
+                *matches.get_one::<TiePolicy>("ties").expect("CLI parsing gone wrong"))
+        };
+        let hotspots = call_hotspots(&graph, &node_c, cutoff, max_gap, unit);
+        log_info!("Found {} hotspots.",hotspots.len());
+        hotspots
+    });
+    if let (Some(hotspotpath),Some(hotspots)) = (matches.get_one::<String>("hotspots"),&hotspots) {
+        let mut fl = File::create(hotspotpath).expect("Could not create hotspot file.");
+        write_hotspot_report(&graph, hotspots, &mut fl).expect("Could not write hotspot file.");
+    }
+    if let (Some(reference),Some(gfaf)) = (matches.get_one::<String>("reference"),matches.get_one::<String>("gfa")) {
+        log_info!("Projecting complexities onto reference {reference}.");
+        let (paths,lengths) = reference_paths_gfa(gfaf, reference).expect("Could not read reference paths");
+        if paths.is_empty() {
+            log_warn!("Warning: No path or walk of reference {reference} found.");
         }
+        let projected = project_reference(&graph, &paths, &lengths).expect("Could not project reference");
+        if let Some(bedgraphpath) = matches.get_one::<String>("bedgraph") {
+            let mut fl = BufWriter::new(File::create(bedgraphpath).expect("Could not create bedGraph file."));
+            write_bedgraph(&projected, &node_c, &mut fl).expect("Could not write bedGraph file.");
+        }
+        if let (Some(bedpath),Some(hotspots)) = (hotspot_bed,&hotspots) {
+            //gaps in other units than bp can not be measured on the reference
+            let bed_gap = if unit == ContextUnit::BasePairs { max_gap } else { 0 };
+            let mut fl = File::create(bedpath).expect("Could not create BED file.");
+            write_hotspot_bed(&projected, hotspots, bed_gap, &mut fl).expect("Could not write BED file.");
+        }
+    }
+    if let Some(significancepath) = matches.get_one::<String>("significance") {
+        let params = NullModelParams {
+            randomizations : *matches.get_one("randomizations").expect("CLI parsing gone wrong"),
+            swaps_per_edge : *matches.get_one("swaps").expect("CLI parsing gone wrong"),
+            seed : *matches.get_one("seed").expect("CLI parsing gone wrong")
+        };
+        let significance = null_model_significance(&graph, &node_c, contextlens[0], unit, &params, n_threads);
+        let mut fl = File::create(significancepath).expect("Could not create significance file.");
+        write_significance(&graph, &significance, &params, &mut fl).expect("Could not write significance file.");
+    }
+    if let Some(tablepath) = matches.get_one::<String>("output-table") {
+        let mut fl = File::create(tablepath).expect("Could not create table file.");
+        results.write_table(&graph, &mut fl).expect("Could not write table file.");
+    }
+    if let (Some(lo),Some(hi)) = (matches.get_one::<f64>("lower-percentile"),matches.get_one::<f64>("higher-percentile")) {
+        results.write_percentiles(&graph, *lo, *hi, *matches.get_one::<TiePolicy>("ties").expect("CLI parsing gone wrong"), &mut io::stdout()).expect("Could not write percentiles.");
     } 
-}
\ No newline at end of file
+}
@@ -1,15 +1,33 @@
 use std::fs::File;
 use std::io::{self, Write};
+use std::process::exit;
 use clap::{arg, value_parser, ArgGroup, Command};
+use scj_carp_rust::logging::set_verbosity;
+use scj_carp_rust::{log_error, log_info, log_warn};
 use scj_carp_rust::mbg::*;
 use scj_carp_rust::util::*;
 use scj_carp_rust::rearrangement::{RearrangementGraph,output_ancestral_adj};
-use scj_carp_rust::measure::calc_carp_measure_multithread;
+use scj_carp_rust::ubg::UBG;
+use scj_carp_rust::measure::*;
+use scj_carp_rust::dynamic::DynamicMeasure;
+use scj_carp_rust::cluster::*;
+use scj_carp_rust::gfa::{genome_adjacencies_gfa,genome_adjacencies_unimog};
 
-fn measure_to_file(p : &str, m : usize, nmarkers : usize) {
+fn measure_to_file(p : &str, report : &CarpReport) {
     let mut fl = File::create(p).expect("Could not create measure file");
-    fl.write_all(format!("Number of markers: {}\n",nmarkers).as_bytes()).expect("Could not write to measure file");
-    fl.write_all(format!("Carp index: {}\n",m).as_bytes()).expect("Could not write to measure file");
+    fl.write_all(format!("Number of markers: {}\n",report.markers).as_bytes()).expect("Could not write to measure file");
+    fl.write_all(format!("Carp index: {}\n",report.measure()).as_bytes()).expect("Could not write to measure file");
+}
+
+fn log_measure_diff(label : &str, diff : &MeasureDiff) {
+    for (what,adjs) in [("contested only in multithreaded result",&diff.contested_only_left),
+            (&format!("contested only in {label}"),&diff.contested_only_right),
+            ("uncontested only in multithreaded result",&diff.uncontested_only_left),
+            (&format!("uncontested only in {label}"),&diff.uncontested_only_right)] {
+        for adj in adjs {
+            log_error!("Verification: {adj} {what}.");
+        }
+    }
 }
 
 fn main() {
@@ -25,18 +43,33 @@ fn main() {
         .arg(arg!(-a --"write-ancestor" <p> "Path to write ancestral adjacencies to."))
         .arg(arg!(-m --"write-measure" <p> "Path to write the carp measure to."))
         .arg(arg!(-t --"num-threads" <t> "Number of threads to use to calculate SCJ CARP index.").value_parser(value_parser!(usize)).default_value("1"))
+        .arg(arg!(-q --"quiet" "Only log errors to standard error.").num_args(0))
+        .arg(arg!(-v --"verbose" "Log debugging information to standard error.").num_args(0))
+        .group(ArgGroup::new("verbosity").args(["quiet","verbose"]))
+        .arg(arg!(--"json" "Output the measure and graph statistics as JSON to standard output.").num_args(0))
+        .arg(arg!(--"write-clusters" <p> "Path to write a report of clusters of contested adjacencies to."))
+        .arg(arg!(--"cluster-gfa" <d> "Directory to write each cluster of contested adjacencies to as gfa file."))
+        .arg(arg!(--"sweep-thresholds" <ts> "Comma separated size thresholds for which to report the measure, updating it incrementally.").value_parser(value_parser!(usize)).value_delimiter(',').requires("write-sweep"))
+        .arg(arg!(--"write-sweep" <p> "Path to write the measure for each of the --sweep-thresholds to.").requires("sweep-thresholds"))
+        .arg(arg!(--"verify" "Verify the result against the naive algorithm.").num_args(0))
+        .arg(arg!(--"verify-ubg" "Verify the result against the naive algorithm on the UBG graph backend (implies --verify).").num_args(0))
         .get_matches();
-    
+    set_verbosity(matches.get_flag("quiet"), matches.get_flag("verbose"));
+
     let mut thresh = *matches.get_one(&"size-thresh").expect("CLI Parsing gone wrong");
     let threads = *matches.get_one(&"num-threads").expect("CLI Parsing gone wrong");
     let is_unimog = matches.get_one::<String>("unimog").is_some();
     if is_unimog && thresh > 0 {
-        eprintln!("Warning: Unimog files do not support node sizes. Ignoring --size-thresh flag.");
+        log_warn!("Warning: Unimog files do not support node sizes. Ignoring --size-thresh flag.");
         thresh = 0;
     }
+    if is_unimog && matches.contains_id("sweep-thresholds") {
+        log_error!("Error: Unimog files do not support node sizes. Cannot sweep size thresholds.");
+        exit(1);
+    }
 
-    eprintln!("{}",CARP_LOGO);
-    println!("Reading graph...");
+    log_info!("{}",CARP_LOGO);
+    log_info!("Reading graph...");
     let maybe_graph = match (matches.get_one::<String>("gfa")
             , matches.get_one::<String>("unimog")) {
         (Some(gfaf),_) => MBG::from_gfa(gfaf,true),
@@ -44,22 +77,95 @@ fn main() {
         (_,_) => Err(io::Error::new(io::ErrorKind::Other,"No file specified."))
     };
     let mut graph = maybe_graph.expect("Something went wrong parsing input files");
-    println!("Adding telomeres to complete graph.");
+    log_info!("Adding telomeres to complete graph.");
     graph.fill_telomeres();
     if thresh > 0 {
-        println!("Trimming graph.");
+        log_info!("Trimming graph.");
         graph.trim_multithread(thresh,threads);
         graph.fill_telomeres();
     }
-    println!("Calculating carp measure.");
-    let (contested, uncontested) = calc_carp_measure_multithread(&graph,threads);
-    let m = contested.len();
-    println!("Carp index: {}",m);
-    print!("On {} markers",graph.num_markers());
+    log_info!("Calculating carp measure.");
+    let report = calc_carp_measure_multithread(&graph,threads);
+    if matches.get_flag("json") {
+        println!("{}",report.to_json());
+    } else {
+        println!("Carp index: {}",report.measure());
+        println!("On {} markers",report.markers);
+    }
     if let Some(p)=  matches.get_one::<String>("write-measure") {
-        measure_to_file(p, m,graph.num_markers());
+        measure_to_file(p, &report);
     }
     if let Some(p)=  matches.get_one::<String>("write-ancestor") {
-        output_ancestral_adj(&graph.marker_names(), &uncontested,&mut File::create(p).expect("Could not create output file."));
+        output_ancestral_adj(&graph.marker_names(), &report.uncontested,&mut File::create(p).expect("Could not create output file."));
+    }
+    if let (Some(thresholds),Some(p)) = (matches.get_many::<usize>("sweep-thresholds"),matches.get_one::<String>("write-sweep")) {
+        log_info!("Sweeping size thresholds.");
+        let mut thresholds : Vec<usize> = thresholds.copied().collect();
+        thresholds.sort();
+        let mut fl = File::create(p).expect("Could not create sweep file.");
+        writeln!(fl,"#Size threshold\tMarkers\tCarp index").expect("Could not write sweep file.");
+        let mut dm = DynamicMeasure::new(graph.clone());
+        for t in thresholds {
+            dm.trim(t);
+            writeln!(fl,"{t}\t{}\t{}",dm.graph().num_markers(),dm.measure()).expect("Could not write sweep file.");
+        }
+    }
+    let cluster_report = matches.get_one::<String>("write-clusters");
+    let cluster_dir = matches.get_one::<String>("cluster-gfa");
+    if cluster_report.is_some() || cluster_dir.is_some() {
+        log_info!("Clustering contested adjacencies.");
+        let mut clusters = conflict_clusters(&graph, &report.contested);
+        log_info!("Found {} clusters.",clusters.len());
+        let of_interest = report.contested.iter().copied().collect();
+        let genomes = match (matches.get_one::<String>("gfa"), matches.get_one::<String>("unimog")) {
+            (Some(gfaf),_) => genome_adjacencies_gfa(gfaf, &graph, &of_interest),
+            (_,Some(unimog)) => genome_adjacencies_unimog(unimog, &graph, &of_interest),
+            (_,_) => Err(io::Error::other("No file specified."))
+        }.expect("Could not read genomes from input file");
+        annotate_genomes(&mut clusters, &genomes);
+        if let Some(p) = cluster_report {
+            write_cluster_report(&graph, &clusters, &mut File::create(p).expect("Could not create cluster report file.")).expect("Could not write cluster report.");
+        }
+        if let Some(d) = cluster_dir {
+            write_cluster_gfas(&graph, &clusters, d).expect("Could not write cluster gfa files.");
+        }
+    }
+    let verify_ubg = matches.get_flag("verify-ubg");
+    if matches.get_flag("verify") || verify_ubg {
+        let mut consistent = true;
+        log_info!("Verifying result.");
+        let dups = report_duplicates(&graph, &report);
+        for adj in &dups {
+            log_error!("Verification: {adj} occurs more than once in multithreaded result.");
+        }
+        consistent &= dups.is_empty();
+        log_info!("Calculating carp measure with naive algorithm.");
+        let naive = calc_carp_measure_naive(&graph);
+        let diff = compare_measures(&graph, &report, &graph, &naive);
+        log_measure_diff("naive result", &diff);
+        consistent &= diff.is_empty();
+        if verify_ubg {
+            log_info!("Reading graph into UBG backend.");
+            let mut ubg = match (matches.get_one::<String>("gfa"), matches.get_one::<String>("unimog")) {
+                (Some(gfaf),_) => UBG::from_gfa(gfaf,true),
+                (_,Some(unimog)) => UBG::from_unimog(unimog),
+                (_,_) => Err(io::Error::other("No file specified."))
+            }.expect("Something went wrong parsing input files");
+            ubg.fill_telomeres();
+            if thresh > 0 {
+                ubg.trim_singlethread(thresh);
+                ubg.fill_telomeres();
+            }
+            log_info!("Calculating carp measure with naive algorithm on UBG backend.");
+            let naive_ubg = calc_carp_measure_naive(&ubg);
+            let diff = compare_measures(&graph, &report, &ubg, &naive_ubg);
+            log_measure_diff("naive UBG result", &diff);
+            consistent &= diff.is_empty();
+        }
+        if !consistent {
+            log_error!("Error: Verification failed.");
+            exit(1);
+        }
+        log_info!("Verification successful.");
     }
 }
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use crate::rearrangement::*;
+use crate::scan::*;
+
+/// Default number of markers scanned between two writes of a checkpoint.
+pub const CHECKPOINT_INTERVAL : usize = 10000;
+
+/// Neighborhoods of completed markers, stored on disk so that an interrupted scan can be resumed.
+///
+/// The checkpoint file starts with the context unit and lengths, followed by one line per completed
+/// marker and flank: the flank, the name of the marker and, for each context length, the comma separated
+/// complexity, adjacencies, markers and span of its neighborhood. Records are appended after each batch
+/// of `interval` markers, a record cut off by an interruption is discarded when resuming.
+pub struct Checkpoint {
+    file : File,
+    contextlens : Vec<usize>,
+    unit : ContextUnit,
+    interval : usize,
+    done : Vec<(Flank,HashMap<Marker,Vec<NeighborhoodStats>>)>
+}
+
+fn parse_flank(s : &str) -> Option<Flank> {
+    match s {
+        "both" => Some(Flank::Both),
+        "head" => Some(Flank::Head),
+        "tail" => Some(Flank::Tail),
+        _ => None
+    }
+}
+
+fn parse_stats(s : &str) -> Option<NeighborhoodStats> {
+    let values : Vec<usize> = s.split(',').map(|v| v.parse().ok()).collect::<Option<Vec<usize>>>()?;
+    match values[..] {
+        [complexity,adjacencies,markers,span] => Some(NeighborhoodStats { complexity, adjacencies, markers, span }),
+        _ => None
+    }
+}
+
+impl Checkpoint {
+    /// Starts a new checkpoint file at `path`, overwriting an existing one.
+    pub fn create(path : &str, contextlens : &[usize], unit : ContextUnit, interval : usize) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        let lens : Vec<String> = contextlens.iter().map(|c| c.to_string()).collect();
+        writeln!(file,"#Context unit: {}",unit.name())?;
+        writeln!(file,"#Context length: {}",lens.join(","))?;
+        file.sync_data()?;
+        Ok(Checkpoint { file, contextlens : contextlens.to_vec(), unit, interval : interval.max(1), done : Vec::new() })
+    }
+
+    /// Loads the completed markers from the checkpoint file at `path` and continues writing to it.
+    /// Fails if the checkpoint was written with other context lengths or unit, or for another graph.
+    pub fn resume(path : &str, graph : &impl RearrangementGraph, contextlens : &[usize], unit : ContextUnit, interval : usize) -> io::Result<Self> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        let lens : Vec<String> = contextlens.iter().map(|c| c.to_string()).collect();
+        let header = format!("#Context unit: {}\n#Context length: {}\n",unit.name(),lens.join(","));
+        if !content.starts_with(&header) {
+            return Err(io::Error::other(format!("Checkpoint {path} was written with other context lengths or unit.")));
+        }
+        let mut done : Vec<(Flank,HashMap<Marker,Vec<NeighborhoodStats>>)> = Vec::new();
+        let mut valid_len = header.len();
+        for line in content[header.len()..].split_inclusive('\n') {
+            let Some(line) = line.strip_suffix('\n') else {
+                break;
+            };
+            let fields : Vec<&str> = line.split('\t').collect();
+            let record = match (fields.first().and_then(|f| parse_flank(f)),fields.get(1)) {
+                (Some(flank),Some(name)) if fields.len() == 2+contextlens.len() => {
+                    fields[2..].iter().map(|s| parse_stats(s)).collect::<Option<Vec<_>>>().map(|stats| (flank,*name,stats))
+                },
+                (_,_) => None
+            };
+            let Some((flank,name,stats)) = record else {
+                return Err(io::Error::other(format!("Malformed checkpoint {path}: {line}")));
+            };
+            let m = graph.name_to_marker(name)
+                .ok_or_else(|| io::Error::other(format!("Node {name} of checkpoint {path} is not part of the (trimmed) graph.")))?;
+            match done.iter_mut().find(|(f,_)| *f == flank) {
+                Some((_,hm)) => { hm.insert(m, stats); },
+                None => done.push((flank,HashMap::from([(m,stats)])))
+            }
+            valid_len += line.len()+1;
+        }
+        let file = OpenOptions::new().append(true).open(path)?;
+        //discard a record that was cut off when the scan was interrupted
+        file.set_len(valid_len as u64)?;
+        Ok(Checkpoint { file, contextlens : contextlens.to_vec(), unit, interval : interval.max(1), done })
+    }
+
+    /// Number of markers completed for `flank`.
+    pub fn completed(&self, flank : Flank) -> usize {
+        self.done.iter().find(|(f,_)| *f == flank).map(|(_,hm)| hm.len()).unwrap_or(0)
+    }
+
+    /// Like `scan_markers_multithread`, but skips markers completed for `flank` and writes the results
+    /// to the checkpoint after each batch. The result contains both, the completed and the newly scanned markers.
+    pub fn scan(&mut self, graph : &impl RearrangementGraph, flank : Flank, markers : &[Marker], n_threads : usize) -> io::Result<HashMap<Marker,Vec<NeighborhoodStats>>> {
+        let mnames = graph.marker_names();
+        let idx = match self.done.iter().position(|(f,_)| *f == flank) {
+            Some(i) => i,
+            None => {
+                self.done.push((flank,HashMap::new()));
+                self.done.len()-1
+            }
+        };
+        let todo : Vec<Marker> = markers.iter().filter(|m| !self.done[idx].1.contains_key(m)).copied().collect();
+        if todo.len() < markers.len() {
+            log_info!("Resuming {} flank scan, {} of {} markers are completed.",flank.name(),markers.len()-todo.len(),markers.len());
+        }
+        for batch in todo.chunks(self.interval) {
+            let results = scan_markers_multithread(graph, &self.contextlens, self.unit, flank, batch, n_threads);
+            let mut records = String::new();
+            for m in batch {
+                let stats : Vec<String> = results[m].iter()
+                    .map(|n| format!("{},{},{},{}",n.complexity,n.adjacencies,n.markers,n.span))
+                    .collect();
+                records.push_str(&format!("{}\t{}\t{}\n",flank.name(),mnames[m],stats.join("\t")));
+            }
+            self.file.write_all(records.as_bytes())?;
+            self.file.sync_data()?;
+            self.done[idx].1.extend(results);
+            log_debug!("Checkpointed {} {} flank neighborhoods.",self.done[idx].1.len(),flank.name());
+        }
+        Ok(markers.iter().map(|m| (*m,self.done[idx].1[m].clone())).collect())
+    }
+}
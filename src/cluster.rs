@@ -0,0 +1,106 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use crate::rearrangement::*;
+use crate::gfa::write_partial_gfa;
+
+/// A connected group of contested adjacencies, i.e. adjacencies that are
+/// linked to each other by sharing extremities.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ConflictCluster {
+    pub adjacencies : Vec<Adjacency>,
+    pub markers : Vec<Marker>,
+    pub length : usize,
+    pub genomes : BTreeSet<String>
+}
+
+impl ConflictCluster {
+    pub fn size(&self) -> usize {
+        self.adjacencies.len()
+    }
+}
+
+pub(crate) fn find_root(parents : &mut HashMap<Extremity,Extremity>, x : Extremity) -> Extremity {
+    let mut root = x;
+    while let Some(p) = parents.get(&root).copied() {
+        if p == root {
+            break;
+        }
+        root = p;
+    }
+    //path compression
+    let mut y = x;
+    while y != root {
+        let p = parents.insert(y, root).unwrap_or(root);
+        y = p;
+    }
+    root
+}
+
+/// Groups contested adjacencies into clusters of adjacencies sharing extremities.
+/// Clusters are sorted by decreasing size, ties are broken by their smallest adjacency.
+pub fn conflict_clusters(graph : &impl RearrangementGraph, contested : &[Adjacency]) -> Vec<ConflictCluster> {
+    let mut parents : HashMap<Extremity,Extremity> = HashMap::new();
+    for (x,y) in contested {
+        parents.entry(*x).or_insert(*x);
+        parents.entry(*y).or_insert(*y);
+        let rx = find_root(&mut parents, *x);
+        let ry = find_root(&mut parents, *y);
+        if rx != ry {
+            parents.insert(rx.max(ry), rx.min(ry));
+        }
+    }
+    let mut members : BTreeMap<Extremity,Vec<Adjacency>> = BTreeMap::new();
+    for adj in contested {
+        let root = find_root(&mut parents, adj.0);
+        members.entry(root).or_default().push(canonicize(*adj));
+    }
+    let mut clusters : Vec<ConflictCluster> = members.into_values().map(|mut adjacencies| {
+        adjacencies.sort();
+        adjacencies.dedup();
+        let markers : BTreeSet<Marker> = adjacencies.iter()
+            .flat_map(|(x,y)| [*x,*y])
+            .filter(|x| *x != TELOMERE)
+            .map(marker)
+            .collect();
+        let length = markers.iter().map(|m| graph.node_size(*m).unwrap_or(0)).sum();
+        ConflictCluster { adjacencies, markers : markers.into_iter().collect(), length, genomes : BTreeSet::new() }
+    }).collect();
+    clusters.sort_by(|a,b| b.size().cmp(&a.size()).then_with(|| a.adjacencies[0].cmp(&b.adjacencies[0])));
+    clusters
+}
+
+/// Annotates each cluster with the genomes that contain at least one of its adjacencies.
+pub fn annotate_genomes(clusters : &mut [ConflictCluster], genomes : &HashMap<Adjacency,HashSet<String>>) {
+    for cluster in clusters.iter_mut() {
+        for adj in &cluster.adjacencies {
+            if let Some(g) = genomes.get(adj) {
+                cluster.genomes.extend(g.iter().cloned());
+            }
+        }
+    }
+}
+
+/// Writes a tab separated report with one line per cluster.
+pub fn write_cluster_report(graph : &impl RearrangementGraph, clusters : &[ConflictCluster], out : &mut impl Write) -> io::Result<()> {
+    let names = graph.marker_names();
+    writeln!(out,"#Cluster\tAdjacencies\tMarkers\tLength\tGenomes\tMember markers")?;
+    for (i,cluster) in clusters.iter().enumerate() {
+        let genomes = cluster.genomes.iter().cloned().collect::<Vec<_>>().join(",");
+        let markers = cluster.markers.iter().map(|m| names.get(m).cloned().unwrap_or_else(|| m.to_string())).collect::<Vec<_>>().join(",");
+        writeln!(out,"{i}\t{}\t{}\t{}\t{genomes}\t{markers}",cluster.size(),cluster.markers.len(),cluster.length)?;
+    }
+    Ok(())
+}
+
+/// Writes every cluster as subgraph `cluster_<i>.gfa` to the given directory.
+pub fn write_cluster_gfas(graph : &impl RearrangementGraph, clusters : &[ConflictCluster], dir : &str) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (i,cluster) in clusters.iter().enumerate() {
+        let mut fl = File::create(Path::new(dir).join(format!("cluster_{i}.gfa")))?;
+        let adjacencies : HashSet<Adjacency> = cluster.adjacencies.iter().copied().collect();
+        write_partial_gfa(graph, &adjacencies, &mut fl)?;
+    }
+    Ok(())
+}
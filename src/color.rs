@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use crate::rearrangement::*;
+
+/// How complexities are mapped onto the color range.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ColorScale {
+    Linear,
+    /// Logarithm of the complexity above the minimum, so that the many low complexities are distinguishable.
+    Log,
+    /// Percentile rank of the complexity among all markers.
+    Rank
+}
+
+impl ColorScale {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorScale::Linear => "linear",
+            ColorScale::Log => "log",
+            ColorScale::Rank => "rank"
+        }
+    }
+
+    pub fn from_name(name : &str) -> Option<Self> {
+        [ColorScale::Linear,ColorScale::Log,ColorScale::Rank].into_iter().find(|s| s.name() == name)
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Palette {
+    /// Ramp from blue over red to green, see `to_heat_html`.
+    Heat,
+    Viridis,
+    Grayscale,
+    /// Diverging palette from blue over gray to red.
+    BlueRed
+}
+
+impl Palette {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Heat => "heat",
+            Palette::Viridis => "viridis",
+            Palette::Grayscale => "grayscale",
+            Palette::BlueRed => "bluered"
+        }
+    }
+
+    pub fn from_name(name : &str) -> Option<Self> {
+        [Palette::Heat,Palette::Viridis,Palette::Grayscale,Palette::BlueRed].into_iter().find(|p| p.name() == name)
+    }
+
+    fn stops(&self) -> &'static [(u8,u8,u8)] {
+        match self {
+            Palette::Heat => &[],
+            Palette::Viridis => &[(0x44,0x01,0x54),(0x3b,0x52,0x8b),(0x21,0x91,0x8c),(0x5e,0xc9,0x62),(0xfd,0xe7,0x25)],
+            Palette::Grayscale => &[(0xe0,0xe0,0xe0),(0x00,0x00,0x00)],
+            Palette::BlueRed => &[(0x3b,0x4c,0xc0),(0xdd,0xdd,0xdd),(0xb4,0x04,0x26)]
+        }
+    }
+
+    /// Html color of position `x` (in `[0,1]`) of the palette.
+    pub fn color(&self, x : f64) -> String {
+        let x = if x.is_nan() { 0.0 } else { x.clamp(0.0,1.0) };
+        if *self == Palette::Heat {
+            return to_heat_html(x);
+        }
+        let stops = self.stops();
+        let pos = x*(stops.len()-1) as f64;
+        let i = (pos.floor() as usize).min(stops.len()-2);
+        let t = pos-i as f64;
+        let mix = |a : u8, b : u8| (a as f64+(b as f64-a as f64)*t).round() as u8;
+        let ((r1,g1,b1),(r2,g2,b2)) = (stops[i],stops[i+1]);
+        format!("#{:02x}{:02x}{:02x}",mix(r1,r2),mix(g1,g2),mix(b1,b2))
+    }
+}
+
+pub fn to_heat_html( x :f64) -> String {
+
+    let redval = (255.0*x.min(0.5)*2.0) as u8;
+    let greenval = (255.0*(x-0.5).max(0.0)*2.0) as u8;
+    let blueval = 110_u8;
+    format!("#{:02x}{:02x}{:02x}",redval,greenval,blueval)
+}
+
+/// Choice of color scale and palette. Fixed minimum and maximum complexities make the colors of different
+/// graphs comparable, complexities outside of them get the color of the closest bound.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct ColorMapParams {
+    pub scale : ColorScale,
+    pub palette : Palette,
+    pub min : Option<usize>,
+    pub max : Option<usize>
+}
+
+impl Default for ColorMapParams {
+    fn default() -> Self {
+        ColorMapParams { scale : ColorScale::Log, palette : Palette::Heat, min : None, max : None }
+    }
+}
+
+impl ColorMapParams {
+    /// Color map for the given complexities. Bounds that are not fixed are taken from the complexities.
+    pub fn fit(&self, node_complexities : &HashMap<Marker,usize>) -> ColorMap {
+        let lo = self.min.unwrap_or_else(|| node_complexities.values().min().copied().unwrap_or(0));
+        let hi = self.max.unwrap_or_else(|| node_complexities.values().max().copied().unwrap_or(0)).max(lo);
+        let mut ranked : Vec<(usize,usize)> = Vec::new();
+        if self.scale == ColorScale::Rank {
+            let mut values : Vec<usize> = node_complexities.values().map(|c| (*c).clamp(lo,hi)).collect();
+            values.sort();
+            for (i,c) in values.into_iter().enumerate() {
+                if ranked.last().is_none_or(|(last,_)| *last != c) {
+                    ranked.push((c,i));
+                }
+            }
+        }
+        ColorMap { params : *self, lo, hi, ranked }
+    }
+}
+
+/// Color map fitted to a set of complexities.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ColorMap {
+    pub params : ColorMapParams,
+    /// Complexities mapped to the first and last color of the palette.
+    pub lo : usize,
+    pub hi : usize,
+    /// For the rank scale: distinct complexities with the number of lower complexities.
+    ranked : Vec<(usize,usize)>
+}
+
+impl ColorMap {
+    /// Position of complexity `c` in `[0,1]` on the color scale.
+    pub fn position(&self, c : usize) -> f64 {
+        let c = c.clamp(self.lo,self.hi);
+        if self.hi == self.lo {
+            return 0.0;
+        }
+        match self.params.scale {
+            ColorScale::Linear => (c-self.lo) as f64/(self.hi-self.lo) as f64,
+            ColorScale::Log => ((c-self.lo) as f64).ln_1p()/((self.hi-self.lo) as f64).ln_1p(),
+            ColorScale::Rank => {
+                let max_rank = self.ranked.last().map(|(_,r)| *r).unwrap_or(0);
+                if max_rank == 0 {
+                    return 0.0;
+                }
+                let i = self.ranked.partition_point(|(v,_)| *v < c);
+                self.ranked.get(i).map(|(_,r)| *r as f64/max_rank as f64).unwrap_or(1.0)
+            }
+        }
+    }
+
+    /// Complexity at position `x` of the color scale, inverse of `position`.
+    pub fn value_at(&self, x : f64) -> f64 {
+        let span = (self.hi-self.lo) as f64;
+        match self.params.scale {
+            ColorScale::Linear => self.lo as f64+x*span,
+            ColorScale::Log => self.lo as f64+(x*span.ln_1p()).exp_m1(),
+            ColorScale::Rank => {
+                let max_rank = self.ranked.last().map(|(_,r)| *r).unwrap_or(0);
+                self.ranked.iter().find(|(_,r)| *r as f64 >= x*max_rank as f64).map(|(v,_)| *v as f64).unwrap_or(self.hi as f64)
+            }
+        }
+    }
+
+    pub fn color(&self, c : usize) -> String {
+        self.params.palette.color(self.position(c))
+    }
+
+    /// Writes a vertical color bar with the complexities at its ticks as SVG.
+    pub fn write_legend_svg(&self, title : &str, out : &mut impl Write) -> io::Result<()> {
+        const STEPS : usize = 100;
+        const TICKS : usize = 5;
+        let (bar_x,bar_y,bar_w,bar_h) = (20,40,30,300);
+        writeln!(out,"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"{}\" font-family=\"sans-serif\" font-size=\"12\">",bar_y+bar_h+40)?;
+        writeln!(out,"<text x=\"{bar_x}\" y=\"20\">{title}</text>")?;
+        //highest complexities on top
+        for i in 0..STEPS {
+            let x = (STEPS-1-i) as f64/(STEPS-1) as f64;
+            let y = bar_y as f64+i as f64*bar_h as f64/STEPS as f64;
+            writeln!(out,"<rect x=\"{bar_x}\" y=\"{y:.2}\" width=\"{bar_w}\" height=\"{:.2}\" fill=\"{}\"/>",bar_h as f64/STEPS as f64+0.5,self.params.palette.color(x))?;
+        }
+        for t in 0..TICKS {
+            let x = t as f64/(TICKS-1) as f64;
+            let y = bar_y as f64+(1.0-x)*bar_h as f64;
+            let tick_x = bar_x+bar_w;
+            writeln!(out,"<line x1=\"{tick_x}\" y1=\"{y:.2}\" x2=\"{}\" y2=\"{y:.2}\" stroke=\"black\"/>",tick_x+5)?;
+            writeln!(out,"<text x=\"{}\" y=\"{:.2}\">{:.0}</text>",tick_x+8,y+4.0,self.value_at(x))?;
+        }
+        writeln!(out,"<text x=\"{bar_x}\" y=\"{}\">{} scale, {}</text>",bar_y+bar_h+25,self.params.scale.name(),self.params.palette.name())?;
+        writeln!(out,"</svg>")
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use crate::rearrangement::*;
+use crate::scan::percentile_ranks;
+
+/// Label of the complexity column in the tables written by `carp-scan --output-table`.
+pub const COMPLEXITY_COLUMN : &str = "SCJ-CARP-measure in env";
+
+/// Complexities of the nodes in a scan result, keyed by node name.
+pub fn named_complexities(graph : &impl RearrangementGraph, node_complexities : &HashMap<Marker,usize>) -> HashMap<String,usize> {
+    let names = graph.marker_names();
+    node_complexities.iter().map(|(m,ci)| (names[m].clone(),*ci)).collect()
+}
+
+/// Reads the complexities from a table written by `carp-scan --output-table`. If the table
+/// contains several context lengths, the first one is used.
+pub fn read_scan_table(path : &str) -> io::Result<HashMap<String,usize>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut column = None;
+    let mut complexities = HashMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(header) = line.strip_prefix("#Node") {
+            column = header.split('\t').position(|label| label.starts_with(COMPLEXITY_COLUMN));
+            continue;
+        }
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        let col = column.ok_or_else(|| io::Error::other(format!("No {COMPLEXITY_COLUMN} column in header of {path}.")))?;
+        let fields : Vec<&str> = line.split('\t').collect();
+        let ci = fields.get(col).and_then(|f| f.parse().ok())
+            .ok_or_else(|| io::Error::other(format!("Malformed line in {path}: {line}")))?;
+        complexities.insert(fields[0].to_string(), ci);
+    }
+    Ok(complexities)
+}
+
+/// Complexity and percentile rank of a node in two scans.
+#[derive(Debug,Clone,PartialEq)]
+pub struct NodeComparison {
+    pub name : String,
+    pub left : usize,
+    pub right : usize,
+    pub left_rank : f64,
+    pub right_rank : f64
+}
+
+impl NodeComparison {
+    pub fn difference(&self) -> i64 {
+        self.right as i64 - self.left as i64
+    }
+
+    pub fn rank_shift(&self) -> f64 {
+        self.right_rank - self.left_rank
+    }
+}
+
+#[derive(Debug,Clone,Default)]
+pub struct ScanComparison {
+    /// Nodes in both scans, sorted by decreasing absolute difference and then by name.
+    pub shared : Vec<NodeComparison>,
+    pub only_left : Vec<String>,
+    pub only_right : Vec<String>
+}
+
+impl ScanComparison {
+    pub fn increased(&self) -> usize {
+        self.shared.iter().filter(|c| c.difference() > 0).count()
+    }
+
+    pub fn decreased(&self) -> usize {
+        self.shared.iter().filter(|c| c.difference() < 0).count()
+    }
+
+    pub fn mean_difference(&self) -> f64 {
+        if self.shared.is_empty() {
+            return 0.0;
+        }
+        self.shared.iter().map(|c| c.difference()).sum::<i64>() as f64 / self.shared.len() as f64
+    }
+
+    pub fn mean_absolute_difference(&self) -> f64 {
+        if self.shared.is_empty() {
+            return 0.0;
+        }
+        self.shared.iter().map(|c| c.difference().abs()).sum::<i64>() as f64 / self.shared.len() as f64
+    }
+
+    pub fn mean_absolute_rank_shift(&self) -> f64 {
+        if self.shared.is_empty() {
+            return 0.0;
+        }
+        self.shared.iter().map(|c| c.rank_shift().abs()).sum::<f64>() / self.shared.len() as f64
+    }
+}
+
+/// Compares two scans by node name. Percentile ranks are calculated within each scan.
+pub fn compare_scans(left : &HashMap<String,usize>, right : &HashMap<String,usize>) -> ScanComparison {
+    let left_ranks = percentile_ranks(left);
+    let right_ranks = percentile_ranks(right);
+    let mut cmp = ScanComparison::default();
+    for (name,l) in left {
+        match right.get(name) {
+            Some(r) => cmp.shared.push(NodeComparison {
+                name : name.clone(),
+                left : *l,
+                right : *r,
+                left_rank : left_ranks[name],
+                right_rank : right_ranks[name]
+            }),
+            None => cmp.only_left.push(name.clone())
+        }
+    }
+    cmp.only_right = right.keys().filter(|name| !left.contains_key(*name)).cloned().collect();
+    cmp.shared.sort_by(|a,b| b.difference().abs().cmp(&a.difference().abs()).then_with(|| a.name.cmp(&b.name)));
+    cmp.only_left.sort();
+    cmp.only_right.sort();
+    cmp
+}
+
+pub fn write_comparison_summary(cmp : &ScanComparison, out : &mut impl Write) -> io::Result<()> {
+    writeln!(out,"Shared nodes\t{}",cmp.shared.len())?;
+    writeln!(out,"Nodes only in A\t{}",cmp.only_left.len())?;
+    writeln!(out,"Nodes only in B\t{}",cmp.only_right.len())?;
+    writeln!(out,"Increased complexity\t{}",cmp.increased())?;
+    writeln!(out,"Decreased complexity\t{}",cmp.decreased())?;
+    writeln!(out,"Unchanged complexity\t{}",cmp.shared.len()-cmp.increased()-cmp.decreased())?;
+    writeln!(out,"Mean difference\t{:.4}",cmp.mean_difference())?;
+    writeln!(out,"Mean absolute difference\t{:.4}",cmp.mean_absolute_difference())?;
+    writeln!(out,"Mean absolute rank shift\t{:.4}",cmp.mean_absolute_rank_shift())?;
+    //shared nodes are sorted by absolute difference
+    if let Some(c) = cmp.shared.iter().find(|c| c.difference() > 0) {
+        writeln!(out,"Largest increase\t{}\t{}",c.name,c.difference())?;
+    }
+    if let Some(c) = cmp.shared.iter().find(|c| c.difference() < 0) {
+        writeln!(out,"Largest decrease\t{}\t{}",c.name,c.difference())?;
+    }
+    Ok(())
+}
+
+/// Writes one line per node, nodes that occur in only one of the scans have `*` as missing values.
+pub fn write_comparison_table(cmp : &ScanComparison, left : &HashMap<String,usize>, right : &HashMap<String,usize>, out : &mut impl Write) -> io::Result<()> {
+    writeln!(out,"#Node\tComplexity A\tComplexity B\tDifference\tPercentile A\tPercentile B\tRank shift")?;
+    for c in &cmp.shared {
+        writeln!(out,"{}\t{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}",c.name,c.left,c.right,c.difference(),c.left_rank,c.right_rank,c.rank_shift())?;
+    }
+    for name in &cmp.only_left {
+        writeln!(out,"{name}\t{}\t*\t*\t*\t*\t*",left[name])?;
+    }
+    for name in &cmp.only_right {
+        writeln!(out,"{name}\t*\t{}\t*\t*\t*\t*",right[name])?;
+    }
+    Ok(())
+}
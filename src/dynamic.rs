@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+use crate::rearrangement::*;
+use crate::mbg::MBG;
+
+/// Wraps an `MBG` and keeps track of its contested and uncontested adjacencies while
+/// markers are removed or adjacencies are added, so that the SCJ CARP measure is
+/// available in O(1) after each modification.
+#[derive(Debug,Clone)]
+pub struct DynamicMeasure {
+    graph : MBG,
+    contested : usize,
+    uncontested : usize,
+    //markers sorted by size for incremental trimming
+    by_size : Vec<Marker>,
+    trimmed_upto : usize
+}
+
+#[inline(always)]
+fn is_contested(graph : &MBG, (x,y) : Adjacency) -> bool {
+    if x == y {
+        return true;
+    }
+    if x == TELOMERE || y == TELOMERE {
+        return false;
+    }
+    graph.degree(x).unwrap_or(0) > 1 || graph.degree(y).unwrap_or(0) > 1
+}
+
+/// All adjacencies incident to the given extremities. Adjacencies of the telomere are
+/// only included via their other extremity to avoid iterating over all telomeres.
+fn incident_adjacencies(graph : &MBG, extremities : &HashSet<Extremity>) -> HashSet<Adjacency> {
+    let mut adjacencies = HashSet::new();
+    for x in extremities {
+        if *x == TELOMERE {
+            continue;
+        }
+        if let Some(neighbors) = graph.adj_neighbors(*x) {
+            for y in neighbors {
+                adjacencies.insert(canonicize((*x,y)));
+            }
+        }
+    }
+    adjacencies
+}
+
+impl DynamicMeasure {
+    pub fn new(graph : MBG) -> Self {
+        let mut contested = 0;
+        let mut uncontested = 0;
+        for adj in graph.iter_adjacencies() {
+            if is_contested(&graph, adj) {
+                contested+=1;
+            } else {
+                uncontested+=1;
+            }
+        }
+        let mut by_size : Vec<Marker> = graph.markers().collect();
+        by_size.sort_by_key(|m| (graph.node_size(*m).unwrap_or(0),*m));
+        DynamicMeasure { graph, contested, uncontested, by_size, trimmed_upto : 0 }
+    }
+
+    /// The current SCJ CARP measure, i.e. the number of contested adjacencies.
+    pub fn measure(&self) -> usize {
+        self.contested
+    }
+
+    pub fn uncontested(&self) -> usize {
+        self.uncontested
+    }
+
+    pub fn graph(&self) -> &MBG {
+        &self.graph
+    }
+
+    pub fn into_graph(self) -> MBG {
+        self.graph
+    }
+
+    fn uncount(&mut self, adjacencies : &HashSet<Adjacency>) {
+        for adj in adjacencies {
+            if is_contested(&self.graph, *adj) {
+                self.contested-=1;
+            } else {
+                self.uncontested-=1;
+            }
+        }
+    }
+
+    fn count(&mut self, adjacencies : &HashSet<Adjacency>) {
+        for adj in adjacencies {
+            if is_contested(&self.graph, *adj) {
+                self.contested+=1;
+            } else {
+                self.uncontested+=1;
+            }
+        }
+    }
+
+    /// Removes marker `m` (see `MBG::remove_marker`) and updates the measure.
+    /// Only adjacencies around the marker's neighbors are reclassified.
+    pub fn remove_marker(&mut self, m : Marker) {
+        if m == TELOMERE || self.graph.degree(head(m)).is_none() {
+            return;
+        }
+        let mut affected : HashSet<Extremity> = HashSet::from([head(m),tail(m)]);
+        for x in [head(m),tail(m)] {
+            affected.extend(self.graph.adj_neighbors(x).into_iter().flatten());
+        }
+        let before = incident_adjacencies(&self.graph, &affected);
+        self.uncount(&before);
+        self.graph.remove_marker(m);
+        let after = incident_adjacencies(&self.graph, &affected);
+        self.count(&after);
+    }
+
+    /// Adds adjacency (x,y) (see `MBG::add_adjacency`) and updates the measure.
+    pub fn add_adjacency(&mut self, x : Extremity, y : Extremity) -> bool {
+        let affected = HashSet::from([x,y]);
+        let before = incident_adjacencies(&self.graph, &affected);
+        if !self.graph.add_adjacency(x, y) {
+            return false;
+        }
+        self.uncount(&before);
+        let after = incident_adjacencies(&self.graph, &affected);
+        self.count(&after);
+        true
+    }
+
+    /// Removes all markers smaller than `min_size`. Repeated calls with increasing
+    /// thresholds only process the markers that were not removed before.
+    pub fn trim(&mut self, min_size : usize) {
+        while let Some(m) = self.by_size.get(self.trimmed_upto).copied() {
+            if self.graph.node_size(m).is_some_and(|s| s >= min_size) {
+                break;
+            }
+            self.remove_marker(m);
+            self.trimmed_upto+=1;
+        }
+    }
+}
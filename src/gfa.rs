@@ -1,4 +1,8 @@
 use std::collections::{HashMap,HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use csv::{ReaderBuilder, StringRecord};
+use flate2::read::MultiGzDecoder;
 use crate::rearrangement::*;
 use crate::rearrangement::Marker;
 
@@ -39,6 +43,10 @@ pub fn parse_marker(node_ids: &mut HashMap<String, Marker>, markerstr: &str, cur
 }
 
 pub fn partial2gfa(ubg : &impl RearrangementGraph, adjacencies : &HashSet<Adjacency>) {
+    write_partial_gfa(ubg, adjacencies, &mut io::stdout().lock()).expect("Could not write gfa to stdout");
+}
+
+pub fn write_partial_gfa(ubg : &impl RearrangementGraph, adjacencies : &HashSet<Adjacency>, out : &mut impl Write) -> io::Result<()> {
     let nids = ubg.marker_names();
     let mut nodes = HashMap::new();
     let mut linkstrs = Vec::new();
@@ -70,9 +78,260 @@ pub fn partial2gfa(ubg : &impl RearrangementGraph, adjacencies : &HashSet<Adjace
         if let Some(ns) = ubg.node_size(k) {
             x+=&format!("\tLN:i:{ns}");
         } 
-        println!("{}",x);
+        writeln!(out,"{}",x)?;
     }
     for lstr in linkstrs {
-        println!("{}",lstr)
+        writeln!(out,"{}",lstr)?;
+    }
+    Ok(())
+}
+
+/// Opens a gfa file for reading, decompressing it if the name ends with `.gz`.
+pub fn open_gfa(path : &str) -> io::Result<Box<dyn Read>> {
+    if path.ends_with(".gz") {
+        Ok(Box::new(MultiGzDecoder::new(File::open(path)?)))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// Genome a path or walk belongs to. Path names following the PanSN
+/// convention (`sample#haplotype#contig`) are reduced to their sample.
+/// Segment names of the first and last step of a path (`P`) or walk (`W`) line together with whether the
+/// extremity at the respective end of the path is the tail of the segment. Graph backends add telomeres there.
+pub fn path_end_telomeres(x : &StringRecord) -> Option<[(String,bool);2]> {
+    match x.get(0) {
+        Some("P") => {
+            let pname = x.get(1).expect("Path does not have a name identifier.");
+            let mut path = x.get(2).unwrap_or_else(|| panic!("Path '{pname}' missing mandatory gfa field 3.")).split(|x : char| {x==',' || x==';'});
+            let fst = path.next();
+            let lst = match path.next_back() {
+                Some(a) => Some(a),
+                None => fst
+            };
+            let parse_pend = |x : &str,is_path_end : bool| {
+                let x = x.strip_suffix("\n").unwrap_or(x);
+                assert!(x.ends_with("+") || x.ends_with("-"));
+                let mut xp = x.to_owned();
+                xp.pop();
+                let xtr_is_tail = is_path_end == x.ends_with("-");
+                (xp,xtr_is_tail)};
+            match (fst,lst) {
+                (Some(f),Some(l)) => Some([parse_pend(f,false),parse_pend(l,true)]),
+                (_,_) => None
+            }
+        },
+        Some("W") => {
+            let wlk = x.get(6).expect("Walk line without walk");
+            let pat = |x : char| {x=='>' || x=='<'};
+            let start = wlk.find(pat);
+            let end = wlk.rfind(pat);
+            let mut wlki = wlk[1..].split(pat);
+            let fst = wlki.next();
+            let lst = match wlki.next_back() {
+                Some(a) => Some(a),
+                None => fst
+            };
+            match (fst,lst) {
+                (Some(f),Some(l)) => {
+                    let e_is_tail = wlk.as_bytes()[end.unwrap()] as char == '<';
+                    let s_is_tail = wlk.as_bytes()[start.unwrap()] as char == '>';
+                    Some([(f.to_owned(),s_is_tail),(l.to_owned(),e_is_tail)])
+                },
+                (_,_) => None
+            }
+        },
+        _ => None
+    }
+}
+
+fn path_genome(pname : &str) -> &str {
+    pname.split('#').next().unwrap_or(pname)
+}
+
+fn record_genome_adjacency(graph : &impl RearrangementGraph, of_interest : &HashSet<Adjacency>, genome : &str,
+    last : &mut Option<(bool,Marker)>, step : (bool,&str), genomes : &mut HashMap<Adjacency,HashSet<String>>) {
+    let (is_forward,name) = step;
+    //steps through trimmed markers are bridged, like the adjacencies of the trimmed graph
+    let Some(m) = graph.name_to_marker(name) else {
+        return;
+    };
+    if let Some(prev) = *last {
+        let adj = canonicize(to_adjacency(prev,(is_forward,m)));
+        if of_interest.contains(&adj) {
+            genomes.entry(adj).or_default().insert(genome.to_string());
+        }
+    }
+    *last = Some((is_forward,m));
+}
+
+/// Determines which genomes (paths and walks of a gfa file) contain each of the given adjacencies.
+pub fn genome_adjacencies_gfa(path : &str, graph : &impl RearrangementGraph, of_interest : &HashSet<Adjacency>) -> io::Result<HashMap<Adjacency,HashSet<String>>> {
+    let reader = open_gfa(path)?;
+    let mut rdr = ReaderBuilder::new().has_headers(false).delimiter(b'\t').flexible(true).from_reader(reader);
+    let mut genomes = HashMap::new();
+    for res in rdr.records() {
+        let x = res?;
+        let mut last = None;
+        match x.get(0) {
+            Some("P") => {
+                let pname = x.get(1).ok_or_else(|| io::Error::other("Path without name."))?;
+                let steps = x.get(2).ok_or_else(|| io::Error::other(format!("Path '{pname}' missing mandatory gfa field 3.")))?;
+                for step in steps.split([',',';']) {
+                    let step = step.trim_end();
+                    let (name,is_forward) = if let Some(n) = step.strip_suffix('+') {
+                        (n,true)
+                    } else if let Some(n) = step.strip_suffix('-') {
+                        (n,false)
+                    } else {
+                        return Err(io::Error::other(format!("Malformed step '{step}' in path '{pname}'.")));
+                    };
+                    record_genome_adjacency(graph, of_interest, path_genome(pname), &mut last, (is_forward,name), &mut genomes);
+                }
+            },
+            Some("W") => {
+                let sample = x.get(1).ok_or_else(|| io::Error::other("Walk without sample."))?;
+                let wlk = x.get(6).ok_or_else(|| io::Error::other("Walk line without walk"))?;
+                let mut rest = wlk.trim_end();
+                while let Some(orient) = rest.chars().next() {
+                    rest = &rest[1..];
+                    let end = rest.find(['>','<']).unwrap_or(rest.len());
+                    record_genome_adjacency(graph, of_interest, sample, &mut last, (orient=='>',&rest[..end]), &mut genomes);
+                    rest = &rest[end..];
+                }
+            },
+            _ => continue
+        }
+    }
+    Ok(genomes)
+}
+
+/// Determines which genomes of a unimog file contain each of the given adjacencies.
+pub fn genome_adjacencies_unimog(path : &str, graph : &impl RearrangementGraph, of_interest : &HashSet<Adjacency>) -> io::Result<HashMap<Adjacency,HashSet<String>>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut genomes = HashMap::new();
+    let mut genome = String::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(gname) = line.strip_prefix('>') {
+            genome = gname.trim().to_string();
+            continue;
+        }
+        let mut first = None;
+        let mut last = None;
+        for tok in line.split_whitespace() {
+            if tok.starts_with(')') || tok.starts_with('|') {
+                //circular chromosomes close with an adjacency back to their first marker
+                if let (true,Some((is_forward,name))) = (tok.starts_with(')'),first) {
+                    record_genome_adjacency(graph, of_interest, &genome, &mut last, (is_forward,name), &mut genomes);
+                }
+                break;
+            }
+            let (name,is_forward) = if let Some(n) = tok.strip_prefix('-') {
+                (n,false)
+            } else {
+                (tok.strip_prefix('+').unwrap_or(tok),true)
+            };
+            record_genome_adjacency(graph, of_interest, &genome, &mut last, (is_forward,name), &mut genomes);
+            if first.is_none() {
+                first = Some((is_forward,name));
+            }
+        }
+    }
+    Ok(genomes)
+}
+
+/// Path or walk of a gfa file whose steps define a linear reference sequence.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ReferencePath {
+    /// Name of the reference sequence: the contig of PanSN path names (`sample#haplotype#contig`) or of walks,
+    /// prefixed by `haplotype#` if the reference consists of several haplotypes.
+    pub sequence : String,
+    /// Coordinate of the first step, as given by the walk.
+    pub start : usize,
+    /// Segment name and orientation (`true` if forward) of each step.
+    pub steps : Vec<(String,bool)>
+}
+
+/// Reads the paths and walks of a gfa file belonging to `reference` together with the lengths of all
+/// segments. Paths match if their name or PanSN sample is `reference`, walks if their sample is `reference`
+/// or `reference` is `sample#haplotype#contig`.
+pub fn reference_paths_gfa(path : &str, reference : &str) -> io::Result<(Vec<ReferencePath>,HashMap<String,usize>)> {
+    let reader = open_gfa(path)?;
+    let mut rdr = ReaderBuilder::new().has_headers(false).delimiter(b'\t').flexible(true).from_reader(reader);
+    let mut paths = Vec::new();
+    //haplotype of each path, to tell the contigs of different haplotypes apart
+    let mut haplotypes = Vec::new();
+    let mut lengths = HashMap::new();
+    for res in rdr.records() {
+        let x = res?;
+        match x.get(0) {
+            Some("S") => {
+                let name = x.get(1).ok_or_else(|| io::Error::other("Empty segment label"))?;
+                let mut seg_len = match x.get(2) {
+                    None | Some("*") => 0,
+                    Some(y) => y.len()
+                };
+                for entry in x.iter().skip(3) {
+                    if let Some(lenstr) = entry.strip_prefix(LEN_PREFIX) {
+                        seg_len = lenstr.parse().map_err(|_| io::Error::other(format!("Invalid length: {lenstr}")))?;
+                    }
+                }
+                lengths.insert(name.to_string(), seg_len);
+            },
+            Some("P") => {
+                let pname = x.get(1).ok_or_else(|| io::Error::other("Path without name."))?;
+                if pname != reference && path_genome(pname) != reference {
+                    continue;
+                }
+                let steps = x.get(2).ok_or_else(|| io::Error::other(format!("Path '{pname}' missing mandatory gfa field 3.")))?;
+                let mut ref_steps = Vec::new();
+                for step in steps.split([',',';']) {
+                    let step = step.trim_end();
+                    if let Some(n) = step.strip_suffix('+') {
+                        ref_steps.push((n.to_string(),true));
+                    } else if let Some(n) = step.strip_suffix('-') {
+                        ref_steps.push((n.to_string(),false));
+                    } else {
+                        return Err(io::Error::other(format!("Malformed step '{step}' in path '{pname}'.")));
+                    }
+                }
+                let sequence = pname.rsplit('#').next().unwrap_or(pname).to_string();
+                let pansn : Vec<&str> = pname.split('#').collect();
+                haplotypes.push(if pansn.len() == 3 { pansn[1] } else { "0" }.to_string());
+                paths.push(ReferencePath { sequence, start : 0, steps : ref_steps });
+            },
+            Some("W") => {
+                let sample = x.get(1).ok_or_else(|| io::Error::other("Walk without sample."))?;
+                let haplotype = x.get(2).unwrap_or("0");
+                let sequence = x.get(3).ok_or_else(|| io::Error::other("Walk without sequence id."))?;
+                if sample != reference && format!("{sample}#{haplotype}#{sequence}") != reference {
+                    continue;
+                }
+                let start = match x.get(4) {
+                    None | Some("*") => 0,
+                    Some(s) => s.parse().map_err(|_| io::Error::other(format!("Invalid walk start: {s}")))?
+                };
+                let wlk = x.get(6).ok_or_else(|| io::Error::other("Walk line without walk"))?;
+                let mut ref_steps = Vec::new();
+                let mut rest = wlk.trim_end();
+                while let Some(orient) = rest.chars().next() {
+                    rest = &rest[1..];
+                    let end = rest.find(['>','<']).unwrap_or(rest.len());
+                    ref_steps.push((rest[..end].to_string(),orient=='>'));
+                    rest = &rest[end..];
+                }
+                haplotypes.push(haplotype.to_string());
+                paths.push(ReferencePath { sequence : sequence.to_string(), start, steps : ref_steps });
+            },
+            _ => continue
+        }
+    }
+    //otherwise the same contig of different haplotypes would have overlapping coordinates
+    if haplotypes.iter().any(|h| *h != haplotypes[0]) {
+        for (path,haplotype) in paths.iter_mut().zip(haplotypes) {
+            path.sequence = format!("{haplotype}#{}",path.sequence);
+        }
     }
+    Ok((paths,lengths))
 }
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use crate::scan::{quantile_thresholds, TiePolicy};
+
+/// Number of buckets of histogram plots if no bucketing is requested.
+pub const DEFAULT_PLOT_BUCKETS : usize = 100;
+
+/// Complexities in `[lo,hi)` and the number of markers having them.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Bucket {
+    pub lo : usize,
+    pub hi : usize,
+    pub count : usize
+}
+
+/// Sorts a histogram as returned by `histogram` and optionally groups it into `n_buckets` buckets
+/// covering the range from the lowest to the highest complexity. With `log_scale`, bucket widths grow
+/// exponentially, otherwise they are equal. Without bucketing, there is one bucket per occurring complexity.
+pub fn bucket_histogram(hist : &HashMap<usize,usize>, n_buckets : Option<usize>, log_scale : bool) -> Vec<Bucket> {
+    let mut entries : Vec<(usize,usize)> = hist.iter().map(|(c,n)| (*c,*n)).collect();
+    entries.sort();
+    let (Some(n_buckets),Some((mn,_)),Some((mx,_))) = (n_buckets,entries.first(),entries.last()) else {
+        return entries.into_iter().map(|(c,count)| Bucket { lo : c, hi : c+1, count }).collect();
+    };
+    let range = mx-mn+1;
+    let n_buckets = n_buckets.clamp(1,range);
+    let mut bounds : Vec<usize> = (0..=n_buckets).map(|i| {
+        let x = i as f64/n_buckets as f64;
+        if log_scale {
+            mn+(((range+1) as f64).powf(x).round() as usize)-1
+        } else {
+            mn+(x*range as f64).round() as usize
+        }
+    }).collect();
+    bounds.dedup();
+    let mut buckets : Vec<Bucket> = bounds.windows(2).map(|w| Bucket { lo : w[0], hi : w[1], count : 0 }).collect();
+    for (c,n) in entries {
+        let i = buckets.partition_point(|b| b.hi <= c);
+        buckets[i].count += n;
+    }
+    buckets
+}
+
+/// Writes the buckets as tab separated table. Without bucketing, every line is `complexity\tcount`, as before
+/// bucketing was supported. Bucketed histograms have a header and `lo\thi\tcount` lines with exclusive `hi`,
+/// even if every bucket happens to hold a single complexity.
+pub fn write_histogram(buckets : &[Bucket], bucketed : bool, out : &mut impl Write) -> io::Result<()> {
+    if !bucketed {
+        for b in buckets {
+            writeln!(out,"{}\t{}",b.lo,b.count)?;
+        }
+    } else {
+        writeln!(out,"#From\tTo\tNodes")?;
+        for b in buckets {
+            writeln!(out,"{}\t{}\t{}",b.lo,b.hi,b.count)?;
+        }
+    }
+    Ok(())
+}
+
+/// Histogram outputs of `carp-scan` and `carp-merge`.
+#[derive(Debug,Clone,PartialEq)]
+pub struct HistogramParams {
+    /// Path of the histogram table.
+    pub table : Option<String>,
+    /// Path of the histogram plot.
+    pub svg : Option<String>,
+    /// Number of buckets, the plot uses `DEFAULT_PLOT_BUCKETS` if not given.
+    pub n_buckets : Option<usize>,
+    pub log_x : bool,
+    pub log_y : bool,
+    /// Percentile range to mark in the plot.
+    pub percentiles : Option<(f64,f64)>,
+    pub ties : TiePolicy
+}
+
+/// Writes the histogram table and plot given in `params`.
+pub fn write_histograms(hist : &HashMap<usize,usize>, params : &HistogramParams) -> io::Result<()> {
+    if let Some(path) = &params.table {
+        let mut out = BufWriter::new(File::create(path)?);
+        write_histogram(&bucket_histogram(hist, params.n_buckets, params.log_x), params.n_buckets.is_some(), &mut out)?;
+        out.flush()?;
+    }
+    if let Some(path) = &params.svg {
+        let markers = match params.percentiles {
+            Some((lo,hi)) => percentile_markers(hist, lo, hi, params.ties),
+            None => Vec::new()
+        };
+        let buckets = bucket_histogram(hist, Some(params.n_buckets.unwrap_or(DEFAULT_PLOT_BUCKETS)), params.log_x);
+        let mut out = BufWriter::new(File::create(path)?);
+        write_histogram_svg(&buckets, params.log_y, "SCJ-CARP-measure in env", &markers, &mut out)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Markers for `write_histogram_svg` at the bounds of the complexities between the lower and higher percentile
+/// under the tie policy.
+pub fn percentile_markers(hist : &HashMap<usize,usize>, percentile_low : f64, percentile_high : f64, ties : TiePolicy) -> Vec<(usize,String)> {
+    match quantile_thresholds(hist, percentile_low, percentile_high, ties) {
+        Some((lt,ht)) => vec![(lt,format!("{percentile_low}")),(ht,format!("{percentile_high}"))],
+        None => Vec::new()
+    }
+}
+
+/// Horizontal position of complexity `c` in units of buckets.
+fn bucket_position(buckets : &[Bucket], c : usize) -> f64 {
+    let i = buckets.partition_point(|b| b.hi <= c);
+    match buckets.get(i) {
+        Some(b) if c >= b.lo => i as f64+(c-b.lo) as f64/(b.hi-b.lo) as f64,
+        Some(_) => i as f64,
+        None => buckets.len() as f64
+    }
+}
+
+/// Renders the buckets as bar chart in SVG, optionally with a logarithmic count axis. Each marker is drawn
+/// as vertical line at the given complexity with its label, e.g. for the bounds of a percentile range.
+pub fn write_histogram_svg(buckets : &[Bucket], log_counts : bool, xlabel : &str, markers : &[(usize,String)], out : &mut impl Write) -> io::Result<()> {
+    const TICKS : usize = 5;
+    let (left,top,width,height) = (70.0,20.0,600.0,300.0);
+    let max_count = buckets.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    let scale = |n : usize| if log_counts {
+        (n as f64).ln_1p()/(max_count as f64).ln_1p()
+    } else {
+        n as f64/max_count as f64
+    };
+    let bar_w = width/buckets.len().max(1) as f64;
+    writeln!(out,"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"12\">",left+width+30.0,top+height+60.0)?;
+    for (i,b) in buckets.iter().enumerate() {
+        let h = scale(b.count)*height;
+        writeln!(out,"<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{h:.2}\" fill=\"#3b528b\"><title>{}-{}: {}</title></rect>",
+            left+i as f64*bar_w,top+height-h,bar_w,b.lo,b.hi-1,b.count)?;
+    }
+    writeln!(out,"<line x1=\"{left}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>",top+height,left+width,top+height)?;
+    writeln!(out,"<line x1=\"{left}\" y1=\"{top}\" x2=\"{left}\" y2=\"{}\" stroke=\"black\"/>",top+height)?;
+    if !buckets.is_empty() {
+        for t in 0..TICKS {
+            let i = (t*(buckets.len()-1))/(TICKS-1);
+            let x = left+(i as f64+0.5)*bar_w;
+            writeln!(out,"<text x=\"{x:.2}\" y=\"{}\" text-anchor=\"middle\">{}</text>",top+height+15.0,buckets[i].lo)?;
+        }
+    }
+    for (count,y) in [(0,top+height),(max_count,top)] {
+        writeln!(out,"<text x=\"{}\" y=\"{:.2}\" text-anchor=\"end\">{count}</text>",left-5.0,y+4.0)?;
+    }
+    writeln!(out,"<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{xlabel}</text>",left+width/2.0,top+height+40.0)?;
+    let ylabel = if log_counts { "Number of nodes (log scale)" } else { "Number of nodes" };
+    writeln!(out,"<text x=\"15\" y=\"{}\" text-anchor=\"middle\" transform=\"rotate(-90 15 {})\">{ylabel}</text>",top+height/2.0,top+height/2.0)?;
+    for (c,label) in markers {
+        let x = left+bucket_position(buckets, *c)*bar_w;
+        writeln!(out,"<line x1=\"{x:.2}\" y1=\"{top}\" x2=\"{x:.2}\" y2=\"{}\" stroke=\"#b40426\" stroke-dasharray=\"4,3\"/>",top+height)?;
+        writeln!(out,"<text x=\"{:.2}\" y=\"{}\" fill=\"#b40426\">{label}</text>",x+3.0,top+12.0)?;
+    }
+    writeln!(out,"</svg>")
+}
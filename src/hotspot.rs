@@ -0,0 +1,128 @@
+use std::collections::{BinaryHeap, BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+use crate::rearrangement::*;
+use crate::scan::*;
+use crate::cluster::find_root;
+
+/// Criterion for markers to be part of a hotspot.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum HotspotCutoff {
+    /// Markers whose complexity lies at or above the given percentile under the tie policy, selected like
+    /// `ScanResults::write_percentiles` selects the range from the percentile to 1.
+    Percentile(f64,TiePolicy),
+    /// Markers with at least the given complexity.
+    Complexity(usize)
+}
+
+impl HotspotCutoff {
+    /// Markers that are part of hotspots.
+    pub fn hot_markers(&self, graph : &impl RearrangementGraph, node_complexities : &HashMap<Marker,usize>) -> HashSet<Marker> {
+        match self {
+            HotspotCutoff::Percentile(p,ties) => {
+                //ties are broken by name, as in the percentile output
+                let names = graph.marker_names();
+                let named : HashMap<(&String,Marker),usize> = node_complexities.iter().map(|(m,c)| ((&names[m],*m),*c)).collect();
+                Quantiles::new(&named).range(*p, 1.0, *ties).into_iter().map(|(_,m)| m).collect()
+            },
+            HotspotCutoff::Complexity(c) => node_complexities.iter().filter(|(_,x)| **x >= *c).map(|(m,_)| *m).collect()
+        }
+    }
+}
+
+/// Region of high complexity markers that lie close to each other in the graph.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Hotspot {
+    /// Member markers, sorted by id.
+    pub markers : Vec<Marker>,
+    /// Summed node sizes of the members.
+    pub length : usize,
+    pub peak : usize,
+    pub mean : f64,
+    /// Member with the peak complexity (the smallest one on ties), e.g. as start node for `carp-extract`.
+    pub representative : Marker
+}
+
+/// Finds the hotspot markers within `max_gap` of `m`, i.e. that are separated from `m` by nodes of
+/// at most that total length in `unit`. Only paths through non-hotspot markers are followed.
+fn close_hotspot_markers(graph : &impl RearrangementGraph, m : Marker, hot : &HashSet<Marker>, max_gap : usize, unit : ContextUnit) -> Vec<Marker> {
+    let mut dist : HashMap<Extremity,usize> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    let mut close = Vec::new();
+    for x in [head(m),tail(m)] {
+        dist.insert(x, 0);
+        heap.push(State { cost : 0, position : x });
+    }
+    while let Some(State {cost, position}) = heap.pop() {
+        if cost > dist[&position] {
+            continue;
+        }
+        let Some(neighbors) = graph.adj_neighbors(position) else {
+            continue;
+        };
+        for neighbor in neighbors {
+            if neighbor == TELOMERE {
+                continue;
+            }
+            let n = marker(neighbor);
+            if hot.contains(&n) {
+                if n != m {
+                    close.push(n);
+                }
+                continue;
+            }
+            let ndist = cost + unit.step(position, neighbor, graph);
+            let oend = other(neighbor);
+            if ndist <= max_gap && dist.get(&oend).is_none_or(|d| *d > ndist) {
+                dist.insert(oend, ndist);
+                heap.push(State { cost : ndist, position : oend });
+            }
+        }
+    }
+    close
+}
+
+/// Merges the markers with a complexity of at least the cutoff into hotspots. Two such markers belong to
+/// the same hotspot if they are separated by nodes of at most `max_gap` total length in `unit`, so with a
+/// gap of 0 only adjacent markers are merged. Hotspots are sorted by decreasing peak, then mean complexity.
+pub fn call_hotspots(graph : &impl RearrangementGraph, node_complexities : &HashMap<Marker,usize>, cutoff : HotspotCutoff, max_gap : usize, unit : ContextUnit) -> Vec<Hotspot> {
+    let hot = cutoff.hot_markers(graph, node_complexities);
+    if let Some(threshold) = hot.iter().map(|m| node_complexities[m]).min() {
+        log_info!("Calling hotspots from {} markers with complexity at least {threshold}.",hot.len());
+    }
+    let mut parents : HashMap<Marker,Marker> = hot.iter().map(|m| (*m,*m)).collect();
+    for m in &hot {
+        for n in close_hotspot_markers(graph, *m, &hot, max_gap, unit) {
+            let rm = find_root(&mut parents, *m);
+            let rn = find_root(&mut parents, n);
+            if rm != rn {
+                parents.insert(rm.max(rn), rm.min(rn));
+            }
+        }
+    }
+    let mut members : BTreeMap<Marker,Vec<Marker>> = BTreeMap::new();
+    for m in &hot {
+        let root = find_root(&mut parents, *m);
+        members.entry(root).or_default().push(*m);
+    }
+    let mut hotspots : Vec<Hotspot> = members.into_values().map(|mut markers| {
+        markers.sort();
+        let length = markers.iter().map(|m| graph.node_size(*m).unwrap_or(0)).sum();
+        let peak = markers.iter().map(|m| node_complexities[m]).max().unwrap_or(0);
+        let mean = markers.iter().map(|m| node_complexities[m]).sum::<usize>() as f64 / markers.len() as f64;
+        let representative = *markers.iter().find(|m| node_complexities[m] == peak).unwrap();
+        Hotspot { markers, length, peak, mean, representative }
+    }).collect();
+    hotspots.sort_by(|a,b| b.peak.cmp(&a.peak).then(b.mean.total_cmp(&a.mean)).then(a.representative.cmp(&b.representative)));
+    hotspots
+}
+
+/// Writes a tab separated report with one line per hotspot.
+pub fn write_hotspot_report(graph : &impl RearrangementGraph, hotspots : &[Hotspot], out : &mut impl Write) -> io::Result<()> {
+    let names = graph.marker_names();
+    writeln!(out,"#Hotspot\tRepresentative\tMarkers\tLength\tPeak complexity\tMean complexity\tMember markers")?;
+    for (i,hotspot) in hotspots.iter().enumerate() {
+        let markers = hotspot.markers.iter().map(|m| names[m].clone()).collect::<Vec<_>>().join(",");
+        writeln!(out,"{i}\t{}\t{}\t{}\t{}\t{:.2}\t{markers}",names[&hotspot.representative],hotspot.markers.len(),hotspot.length,hotspot.peak,hotspot.mean)?;
+    }
+    Ok(())
+}
@@ -1,4 +1,5 @@
-
+#[macro_use]
+pub mod logging;
 pub mod rearrangement;
 pub mod ubg;
 pub mod gfa;
@@ -6,5 +7,16 @@ pub mod util;
 pub mod mbg;
 pub mod scan;
 pub mod measure;
+pub mod cluster;
+pub mod dynamic;
+pub mod sample;
+pub mod compare;
+pub mod output;
+pub mod checkpoint;
+pub mod hotspot;
+pub mod reference;
+pub mod significance;
+pub mod color;
+pub mod histogram;
 #[cfg(test)]
 mod tests;
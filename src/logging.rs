@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Severity of a log message. Messages are written to standard error if their
+/// level is at most the globally configured level.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3
+}
+
+static LOG_LEVEL : AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub fn set_log_level(level : LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Sets the log level from the `--quiet`/`--verbose` command line flags.
+pub fn set_verbosity(quiet : bool, verbose : bool) {
+    if quiet {
+        set_log_level(LogLevel::Error);
+    } else if verbose {
+        set_log_level(LogLevel::Debug);
+    } else {
+        set_log_level(LogLevel::Info);
+    }
+}
+
+#[inline(always)]
+pub fn log_enabled(level : LogLevel) -> bool {
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        if $crate::logging::log_enabled($crate::logging::LogLevel::Error) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        if $crate::logging::log_enabled($crate::logging::LogLevel::Warn) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        if $crate::logging::log_enabled($crate::logging::LogLevel::Info) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        if $crate::logging::log_enabled($crate::logging::LogLevel::Debug) {
+            eprintln!($($arg)*);
+        }
+    };
+}
@@ -21,8 +21,9 @@ pub struct MBG {
 
 
 impl MBG {
+    /// Removes marker `m` from the graph, connecting the neighbors of its head to the neighbors of its tail.
     #[inline(always)]
-    fn remove_marker(&mut self, m : Marker) {
+    pub fn remove_marker(&mut self, m : Marker) {
         if self.masked_markers.contains(&m) {
             return
         }
@@ -142,8 +143,8 @@ impl MBG {
             let x = res?;
             i+=1;
             if i%ONE_MILLION==0 {
-                eprintln!("Read {} lines.",i);
-                eprintln!("{} nodes and {} edges in graph.",node_sizes.len(),n_edges);
+                log_info!("Read {} lines.",i);
+                log_info!("{} nodes and {} edges in graph.",node_sizes.len(),n_edges);
             }
     
             let entrytype= match x.get(0) {
@@ -231,53 +232,12 @@ impl MBG {
                     }
             }
                 
-            } else if entrytype == "P" {
-                let pname = x.get(1).expect("Path does not have a name identifier.");
-                let mut path = x.get(2).expect(&format!("Path '{pname}' missing mandatory gfa field 3.")).split(|x : char| {x==',' || x==';'});
-                let fst = path.nth(0);
-                //let lst = path.last();
-                let lst = match path.last() {
-                    Some(a) => Some(a),
-                    None => fst
-                };
-                let parse_pend = |x : &str,is_path_end : bool| {
-                    let x = x.strip_suffix("\n").unwrap_or(x);
-                    assert!(x.ends_with("+") || x.ends_with("-"));
-                    let mut xp = x.to_owned();
-                    xp.pop();
-                    let xtr_is_tail = is_path_end == x.ends_with("-");
-                    (xp,xtr_is_tail)};
-                match (fst,lst) {
-                    (Some(f),Some(l)) => {
-                    telomeres.insert(parse_pend(f,false));
-                    telomeres.insert(parse_pend(l,true));
-                    },
-                    (_,_) => continue
-                }
-            } else if entrytype == "W" {
-                let wlk = x.get(6).expect("Walk line without walk");
-                let pat = |x : char| {x=='>' || x=='<'};
-                let start = wlk.find(pat);
-                let end = wlk.rfind(pat);
-                let mut wlki = wlk[1..].split(pat);
-                let fst = wlki.nth(0);
-                let lst = match wlki.last() {
-                    Some(a) => Some(a),
-                    None => fst
-                };
-                match (fst,lst) {
-                    (Some(f),Some(l)) => {
-                    let e_is_tail = wlk.as_bytes()[end.unwrap()] as char == '<';
-                    let s_is_tail = wlk.as_bytes()[start.unwrap()] as char == '>';
-                    telomeres.insert((f.to_owned(),s_is_tail));
-                    telomeres.insert((l.to_owned(),e_is_tail));
-                    },
-                    (_,_) => continue
-                }
+            } else if entrytype == "P" || entrytype == "W" {
+                telomeres.extend(path_end_telomeres(&x).into_iter().flatten());
             }
 
         }
-        eprintln!("Filling in {} telomeres observed in paths",telomeres.len());
+        log_info!("Filling in {} telomeres observed in paths",telomeres.len());
         for (mrk,xtr_is_tail) in telomeres {
             let m = *node_ids.get(&mrk).expect(&format!("Segment {mrk} occurs in a path, but not as a segment entry."));
             let xtr = if xtr_is_tail {
@@ -294,15 +254,15 @@ impl MBG {
 
         let cigproblem = warned_cigar.is_some();
         if let Some((cigar,errmsg)) = warned_cigar {
-            eprintln!("Warning: Unsupported Cigar string overlap: {cigar}. Error: {errmsg}.");
+            log_warn!("Warning: Unsupported Cigar string overlap: {cigar}. Error: {errmsg}.");
         }
 
         if warned_overlaps {
-            eprintln!("Warning: Unsupported overlaps in Cigar strings: Non-fixed length.")
+            log_warn!("Warning: Unsupported overlaps in Cigar strings: Non-fixed length.");
         }
 
         if warned_overlaps || cigproblem {
-            eprintln!("Warning: all overlaps have been set to 0.")
+            log_warn!("Warning: all overlaps have been set to 0.");
         }
         
         Ok(MBG { node_sizes: node_sizes, adjacencies: adjacencies, node_ids: node_ids, masked_markers: HashSet::from([TELOMERE]) , ext_overlap : x_overlap.unwrap_or(0)})
@@ -338,11 +298,11 @@ impl MBG {
                 let g = &self;
                 let lb = (slice_size*i).min(nmarkers);
                 let rb = (slice_size*(i+1)).min(nmarkers);
-                eprintln!("Spawning thread {i} processing markers with index {lb} to {rb} (total {nmarkers})");
+                log_debug!("Spawning thread {i} processing markers with index {lb} to {rb} (total {nmarkers})");
                 let x =  scope.spawn(move || g.identify_removal_nodes_in_range(min_size, lb, rb));
                 handles.push(x);
             }
-            eprintln!("Joining results.");
+            log_debug!("Joining results.");
             for x in handles {
                 to_remove.extend(x.join().unwrap());
             }
@@ -356,7 +316,7 @@ impl MBG {
         let about_one_percent = (tsize/100).max(1).min(ONE_MILLION/10);
         for (i,m) in to_remove.iter().copied().enumerate() {
             if (i+1)%about_one_percent==0{
-                eprintln!("Removal processed {i}/{tsize} markers.");
+                log_info!("Removal processed {i}/{tsize} markers.");
             }
             self.remove_marker(m);
 
@@ -366,18 +326,18 @@ impl MBG {
     pub fn trim_any(&mut self,min_size: usize, n_threads : usize) {
          
         if n_threads == 1 {
-            eprintln!("Trimming singlethreaded.");
+            log_info!("Trimming singlethreaded.");
             self.trim_singlethread(min_size);
             return;
         }
         let rm = self.identify_removal_nodes_mthread(min_size, n_threads);
         if rm.len() >= self.num_markers()/10 {
-            eprintln!("More then 10% of nodes are scheduled for removal. Trimming singlethreaded.");
+            log_info!("More then 10% of nodes are scheduled for removal. Trimming singlethreaded.");
             if self.ext_overlap > 0 {
                 if  SAFE_GFA_OVERLAP {
                     panic!("Cannot safely trim graph with overlaps.");
                 } else {
-                    eprintln!("Trimming requires 0-Overlap between segments. Overlap will be ignored from now on.");
+                    log_warn!("Trimming requires 0-Overlap between segments. Overlap will be ignored from now on.");
                     self.ext_overlap=0;
                 }
             }
@@ -387,6 +347,64 @@ impl MBG {
         self.trim_multithread(min_size, n_threads);
     }
 
+
+    /// Adds adjacency (x,y) to the graph. Returns false if the adjacency already exists or
+    /// one of the extremities is not (or no longer) part of the graph.
+    pub fn add_adjacency(&mut self, x : Extremity, y : Extremity) -> bool {
+        if (x==TELOMERE && y==TELOMERE) || self.degree(x).is_none() || self.degree(y).is_none() {
+            return false;
+        }
+        if self.adjacencies[x].contains(&y) {
+            return false;
+        }
+        self.adjacencies[x].push(y);
+        //self loops thereby end up twice in the list, as required for degree purposes
+        self.adjacencies[y].push(x);
+        true
+    }
+
+    /// Copy of the graph with randomly rewired adjacencies, e.g. as null model. For `swaps_per_edge` times the
+    /// number of adjacencies, two random adjacencies {a,b} and {c,d} are replaced by {a,d} and {c,b} (or {a,c}
+    /// and {b,d}), unless this would create a self loop or an adjacency that already exists. Thereby the degree
+    /// of each extremity as well as node sizes, telomeric adjacencies and existing self loops are kept.
+    pub fn randomized(&self, swaps_per_edge : usize, rng : &mut SplitMix64) -> MBG {
+        let mut present : HashSet<Adjacency> = self.iter_adjacencies().collect();
+        let mut edges : Vec<Adjacency> = present.iter()
+            .filter(|(x,y)| *x != TELOMERE && *y != TELOMERE && x != y)
+            .copied()
+            .collect();
+        edges.sort();
+        if edges.len() >= 2 {
+            for _ in 0..swaps_per_edge*edges.len() {
+                let i = rng.below(edges.len());
+                let j = rng.below(edges.len());
+                let ((a,b),(c,d)) = (edges[i],edges[j]);
+                let (e,f) = if rng.next_u64() & 1 == 0 {
+                    (canonicize((a,d)),canonicize((c,b)))
+                } else {
+                    (canonicize((a,c)),canonicize((b,d)))
+                };
+                if i == j || e.0 == e.1 || f.0 == f.1 || e == f || present.contains(&e) || present.contains(&f) {
+                    continue;
+                }
+                present.remove(&edges[i]);
+                present.remove(&edges[j]);
+                present.insert(e);
+                present.insert(f);
+                edges[i] = e;
+                edges[j] = f;
+            }
+        }
+        let mut randomized = self.clone();
+        for (x,neighbors) in randomized.adjacencies.iter_mut().enumerate().skip(1) {
+            neighbors.retain(|y| *y == TELOMERE || *y == x);
+        }
+        for (x,y) in edges {
+            randomized.adjacencies[x].push(y);
+            randomized.adjacencies[y].push(x);
+        }
+        randomized
+    }
 }
 
 
@@ -434,7 +452,7 @@ fn __trim_vertices(graph : &MBG, from : Extremity, to : Extremity, size_threshol
             if (i - from)%ONE_MILLION == 0 && i > from {
                 let proc_nodes = i-from;
                 let percentage = proc_nodes as f64 / nnodes as f64 * 100.0;
-                eprintln!("Processed {proc_nodes} extremities in range [{from},{to}[ ({percentage:.02}%)");
+                log_info!("Processed {proc_nodes} extremities in range [{from},{to}[ ({percentage:.02}%)");
             }
         }
         //eprintln!("{from} {to}: {adjacencies:?}");
@@ -505,16 +523,16 @@ impl RearrangementGraph for MBG {
             if  SAFE_GFA_OVERLAP {
                 panic!("Cannot safely trim graph with overlaps.");
             } else {
-                eprintln!("Trimming requires 0-Overlap between segments. Overlap will be ignored from now on.");
+                log_warn!("Trimming requires 0-Overlap between segments. Overlap will be ignored from now on.");
                 self.ext_overlap=0;
             }
         }
         let nbefore = self.num_markers();
         let to_remove = self.identify_removal_nodes(min_size);
-        eprintln!("Identified {} markers for removal.",to_remove.len());
+        log_info!("Identified {} markers for removal.",to_remove.len());
         self.remove_all(&to_remove);
         if self.num_markers() < nbefore/10 {
-            eprintln!("Warning: Only {} markers left after trimming",self.num_markers())
+            log_warn!("Warning: Only {} markers left after trimming",self.num_markers());
         }
     }
 
@@ -554,11 +572,11 @@ impl RearrangementGraph for MBG {
 
     fn from_gfa(path: &str, ignore_overlap : bool) -> io::Result<Self>{
     if !path.ends_with(".gz") {
-        eprintln!("Trying to read uncompressed gfa.");
+        log_info!("Trying to read uncompressed gfa.");
         let mut rdr = ReaderBuilder::new().has_headers(false).delimiter(b'\t').flexible(true).from_path(path)?;
         return Self::gfa_from_any_reader(&mut rdr,ignore_overlap);
     } else{
-        eprintln!("Trying to read compressed gfa.");
+        log_info!("Trying to read compressed gfa.");
         let fl = File::open(path)?;
         let gz = MultiGzDecoder::new(fl);
         let mut rdr =  ReaderBuilder::new().has_headers(false).delimiter(b'\t').flexible(true).from_reader(gz);
@@ -657,7 +675,7 @@ fn trim_multithread(&mut self, min_size : usize, n_threads : usize) {
             if  SAFE_GFA_OVERLAP {
                 panic!("Cannot safely trim graph with overlaps.");
             } else {
-                eprintln!("Trimming requires 0-Overlap between segments. Overlap will be ignored from now on.");
+                log_warn!("Trimming requires 0-Overlap between segments. Overlap will be ignored from now on.");
                 self.ext_overlap=0;
             }
     }
@@ -675,7 +693,7 @@ fn trim_multithread(&mut self, min_size : usize, n_threads : usize) {
             let g = &self;
             let lb = (slice_size*i).min(max_xt);
             let rb = (slice_size*(i+1)).min(max_xt);
-            eprintln!("Spawning thread {i} processing extremities with index {lb} to {rb} (total {max_xt})");
+            log_debug!("Spawning thread {i} processing extremities with index {lb} to {rb} (total {max_xt})");
             let x =  scope.spawn(move || __trim_vertices(g,lb, rb, min_size));
             handles.push(x);
         }
@@ -684,7 +702,7 @@ fn trim_multithread(&mut self, min_size : usize, n_threads : usize) {
         }
         
     });
-    eprintln!("Joining results.");
+    log_debug!("Joining results.");
     for (masked, adj) in results {
         self.masked_markers.extend(&masked);
         for m in &masked {
@@ -696,7 +714,7 @@ fn trim_multithread(&mut self, min_size : usize, n_threads : usize) {
         }
     }
     if self.num_markers() < nbefore/10 {
-            eprintln!("Warning: Only {} markers left after trimming",self.num_markers())
+            log_warn!("Warning: Only {} markers left after trimming",self.num_markers());
     }
 }
     fn overlap(&self, _:Extremity,_:Extremity) -> usize {
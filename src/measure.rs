@@ -1,8 +1,90 @@
-use std::collections::{HashMap, HashSet};
-use std::thread;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use crate::rearrangement::*;
 use crate::util::*;
 
+/// Number of extremities a thread processes at once in `calc_carp_measure_multithread`.
+pub const MEASURE_CHUNK_SIZE : usize = 4096;
+
+
+/// Result of the SCJ CARP measure calculation together with statistics of the graph it was computed on.
+#[derive(Debug,Clone)]
+pub struct CarpReport {
+    pub contested : Vec<Adjacency>,
+    pub uncontested : Vec<Adjacency>,
+    pub markers : usize,
+    pub extremities : usize,
+    pub adjacencies : usize,
+    pub telomeric_adjacencies : usize,
+    pub self_loops : usize,
+    /// Maps each degree to the number of (non-telomere) extremities with that degree.
+    pub degree_distribution : BTreeMap<usize,usize>
+}
+
+impl CarpReport {
+    pub fn new(graph : &impl RearrangementGraph, contested : Vec<Adjacency>, uncontested : Vec<Adjacency>) -> Self {
+        let mut degree_distribution = BTreeMap::new();
+        for x in graph.extremities() {
+            if x == TELOMERE {
+                continue;
+            }
+            *degree_distribution.entry(graph.degree(x).unwrap_or(0)).or_insert(0) += 1;
+        }
+        let telomeric_adjacencies = uncontested.iter().filter(|(x,y)| *x == TELOMERE || *y == TELOMERE).count();
+        let self_loops = contested.iter().filter(|(x,y)| x == y).count();
+        CarpReport {
+            markers : graph.num_markers(),
+            extremities : graph.num_extremities(),
+            adjacencies : contested.len()+uncontested.len(),
+            telomeric_adjacencies,
+            self_loops,
+            degree_distribution,
+            contested,
+            uncontested
+        }
+    }
+
+    /// The SCJ CARP measure, i.e. the number of contested adjacencies.
+    pub fn measure(&self) -> usize {
+        self.contested.len()
+    }
+
+    /// Fraction of non-telomeric adjacencies that are contested.
+    pub fn measure_per_adjacency(&self) -> f64 {
+        let n = self.adjacencies-self.telomeric_adjacencies;
+        if n == 0 {
+            return 0.0;
+        }
+        self.measure() as f64 / n as f64
+    }
+
+    pub fn measure_per_marker(&self) -> f64 {
+        if self.markers == 0 {
+            return 0.0;
+        }
+        self.measure() as f64 / self.markers as f64
+    }
+
+    /// Serializes the report (without the adjacency lists) as a JSON object.
+    pub fn to_json(&self) -> String {
+        let degrees = self.degree_distribution.iter().map(|(d,n)| format!("\"{d}\": {n}")).collect::<Vec<_>>().join(", ");
+        let fields = [
+            ("version",format!("\"{CARP_VERSION}\"")),
+            ("markers",self.markers.to_string()),
+            ("extremities",self.extremities.to_string()),
+            ("adjacencies",self.adjacencies.to_string()),
+            ("telomeric_adjacencies",self.telomeric_adjacencies.to_string()),
+            ("self_loops",self.self_loops.to_string()),
+            ("contested",self.measure().to_string()),
+            ("uncontested",self.uncontested.len().to_string()),
+            ("measure_per_adjacency",self.measure_per_adjacency().to_string()),
+            ("measure_per_marker",self.measure_per_marker().to_string()),
+            ("degree_distribution",format!("{{{degrees}}}"))
+        ];
+        let body = fields.iter().map(|(k,v)| format!("  \"{k}\": {v}")).collect::<Vec<_>>().join(",\n");
+        format!("{{\n{body}\n}}")
+    }
+}
+
 
 pub fn carp_measure_from_adjacencies(adjacencies : &HashSet<Adjacency>) -> usize {
     let mut carp_measure = 0;
@@ -56,39 +138,29 @@ pub fn calc_partial_measure(graph : &impl RearrangementGraph, extremities : &[Ex
     (contested,uncontested)
 }
 
-pub fn calc_carp_measure_multithread(graph : &impl RearrangementGraph, n_threads : usize) -> (Vec<Adjacency>,Vec<Adjacency>) {
+pub fn calc_carp_measure_multithread(graph : &impl RearrangementGraph, n_threads : usize) -> CarpReport {
     let mut contested = Vec::new();
     let mut uncontested = Vec::new();
     let extremities : Vec<Extremity> = graph.extremities().collect();
-    let tot_len = extremities.len();
-    let slice_len = (tot_len/n_threads)+1;
-    thread::scope(|scope| {
-        let mut handles = Vec::new();
-        for i in 0..n_threads {
-            let lb = (i*slice_len).min(tot_len);
-            let rb = ((i+1)*slice_len).min(tot_len);
-            let elist = &extremities[lb..rb];
-            let handle = scope.spawn(move || {
-                calc_partial_measure(graph, elist, i)
-            }
-            );
-            handles.push(handle);
-        }
-        let mut i=0;
-        for handle in handles {
-            eprintln!("Joining {i}.");
-            let (c,uc) =  handle.join().unwrap();
-            i+=1;
-            contested.extend(c);
-            uncontested.extend(uc);
-        }
-     });
-    (contested,uncontested)
+    let results = parallel_chunks(extremities.len(), MEASURE_CHUNK_SIZE, n_threads, "extremities",
+        || (Vec::new(),Vec::new()),
+        |(c,uc) : &mut (Vec<Adjacency>,Vec<Adjacency>),lb,rb,i| {
+            let (pc,puc) = calc_partial_measure(graph, &extremities[lb..rb], i);
+            c.extend(pc);
+            uc.extend(puc);
+        });
+    for (c,uc) in results {
+        contested.extend(c);
+        uncontested.extend(uc);
+    }
+    CarpReport::new(graph, contested, uncontested)
 }
 
 
 
-pub fn calc_carp_measure_naive(graph : &impl RearrangementGraph) -> (HashSet<Adjacency>,HashSet<Adjacency>){
+/// Single threaded calculation of the SCJ CARP measure, used to verify `calc_carp_measure_multithread`.
+/// The adjacencies of the report are sorted.
+pub fn calc_carp_measure_naive(graph : &impl RearrangementGraph) -> CarpReport {
     let mut contested = HashSet::new();
     let mut uncontested = HashSet::new();
     let adjset : HashSet<Adjacency>= graph.iter_adjacencies().collect();
@@ -107,7 +179,7 @@ pub fn calc_carp_measure_naive(graph : &impl RearrangementGraph) -> (HashSet<Adj
                 continue;
             }
             let e =canonicize((nxtr,xtr));
-            eprintln!("{e:?}");
+            log_debug!("{e:?}");
             assert!(adjset.contains(&e));
             if is_contested {
                 contested.insert(e);
@@ -117,6 +189,66 @@ pub fn calc_carp_measure_naive(graph : &impl RearrangementGraph) -> (HashSet<Adj
             }
         }
     }
-    (contested,uncontested)
+    let mut contested : Vec<Adjacency> = contested.into_iter().collect();
+    let mut uncontested : Vec<Adjacency> = uncontested.into_iter().collect();
+    contested.sort_unstable();
+    uncontested.sort_unstable();
+    CarpReport::new(graph, contested, uncontested)
 }
 
+
+fn adjacency_name(names : &HashMap<Marker,String>, (x,y) : Adjacency) -> String {
+    let xtr_name = |x : Extremity| if x == TELOMERE {
+        String::from("telomere")
+    } else {
+        pretty_extremity(names, x)
+    };
+    let (a,b) = (xtr_name(x),xtr_name(y));
+    //marker ids may differ between graphs, so order by name instead
+    if a <= b {
+        a+"-"+&b
+    } else {
+        b+"-"+&a
+    }
+}
+
+fn adjacency_names(graph : &impl RearrangementGraph, adjacencies : &[Adjacency]) -> BTreeSet<String> {
+    let names = graph.marker_names();
+    adjacencies.iter().map(|a| adjacency_name(&names, *a)).collect()
+}
+
+/// Differences between two classifications of adjacencies into contested and uncontested ones.
+/// Adjacencies are identified by marker names, so that classifications on different graph
+/// representations of the same input can be compared.
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct MeasureDiff {
+    pub contested_only_left : Vec<String>,
+    pub contested_only_right : Vec<String>,
+    pub uncontested_only_left : Vec<String>,
+    pub uncontested_only_right : Vec<String>
+}
+
+impl MeasureDiff {
+    pub fn is_empty(&self) -> bool {
+        self.contested_only_left.is_empty() && self.contested_only_right.is_empty()
+            && self.uncontested_only_left.is_empty() && self.uncontested_only_right.is_empty()
+    }
+}
+
+pub fn compare_measures(left : &impl RearrangementGraph, lreport : &CarpReport, right : &impl RearrangementGraph, rreport : &CarpReport) -> MeasureDiff {
+    let (lc,luc) = (adjacency_names(left, &lreport.contested),adjacency_names(left, &lreport.uncontested));
+    let (rc,ruc) = (adjacency_names(right, &rreport.contested),adjacency_names(right, &rreport.uncontested));
+    MeasureDiff {
+        contested_only_left : lc.difference(&rc).cloned().collect(),
+        contested_only_right : rc.difference(&lc).cloned().collect(),
+        uncontested_only_left : luc.difference(&ruc).cloned().collect(),
+        uncontested_only_right : ruc.difference(&luc).cloned().collect()
+    }
+}
+
+/// Names of adjacencies that occur more than once in the contested or uncontested adjacencies of a report.
+pub fn report_duplicates(graph : &impl RearrangementGraph, report : &CarpReport) -> Vec<String> {
+    let names = graph.marker_names();
+    find_dups(&report.contested).into_iter().chain(find_dups(&report.uncontested))
+        .map(|a| adjacency_name(&names, a)).collect()
+}
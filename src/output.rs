@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use crate::rearrangement::*;
+use crate::scan::*;
+use crate::color::ColorMap;
+pub use crate::color::to_heat_html;
+use crate::gfa::open_gfa;
+
+/// Value of a neighborhood output in a column.
+type StatValue = fn(&NeighborhoodStats) -> String;
+
+/// Column of the scan output: label, index of the scan and the value of the neighborhood to output.
+type Column = (&'static str,usize,StatValue);
+
+const NEIGHBORHOOD_COLUMNS : [(&str,StatValue);5] = [
+    ("SCJ-CARP-measure in env",|n| n.complexity.to_string()),
+    ("Adjacencies in env",|n| n.adjacencies.to_string()),
+    ("Markers in env",|n| n.markers.to_string()),
+    ("Span of env",|n| n.span.to_string()),
+    ("Normalized SCJ-CARP-measure in env",|n| format!("{:.4}",n.normalized()))
+];
+
+fn flank_label(flank : Flank) -> &'static str {
+    match flank {
+        Flank::Both => "SCJ-CARP-measure in env",
+        Flank::Head => "Head flank SCJ-CARP-measure in env",
+        Flank::Tail => "Tail flank SCJ-CARP-measure in env"
+    }
+}
+
+/// Results of scanning a graph with one or more context lengths, optionally including the flanks.
+#[derive(Debug,Clone)]
+pub struct ScanResults {
+    pub contextlens : Vec<usize>,
+    pub unit : ContextUnit,
+    /// Neighborhoods of the scanned markers (`Flank::Both`), optionally followed by the head and tail flanks.
+    pub scans : Vec<(Flank,HashMap<Marker,Vec<NeighborhoodStats>>)>
+}
+
+impl ScanResults {
+    pub fn new(contextlens : &[usize], unit : ContextUnit, neighborhoods : HashMap<Marker,Vec<NeighborhoodStats>>) -> Self {
+        ScanResults {
+            contextlens : contextlens.to_vec(),
+            unit,
+            scans : vec![(Flank::Both,neighborhoods)]
+        }
+    }
+
+    /// Complexities of the scanned markers for the first context length.
+    pub fn node_complexities(&self) -> HashMap<Marker,usize> {
+        self.scans[0].1.iter().map(|(m,ns)| (*m,ns[0].complexity)).collect()
+    }
+
+    fn columns(&self) -> Vec<Column> {
+        let mut columns : Vec<Column> = NEIGHBORHOOD_COLUMNS.iter().map(|(label,value)| (*label,0,*value)).collect();
+        for (i,(flank,_)) in self.scans.iter().enumerate().skip(1) {
+            columns.push((flank_label(*flank),i,|n| n.complexity.to_string()));
+        }
+        columns
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        column_labels(&self.columns(), &self.contextlens)
+    }
+
+    fn values(&self, columns : &[Column], m : Marker) -> Vec<String> {
+        columns.iter().flat_map(|(_,i,value)| self.scans[*i].1[&m].iter().map(value)).collect()
+    }
+
+    /// Writes the results of all scanned markers sorted by name.
+    pub fn write_table(&self, graph : &impl RearrangementGraph, out : &mut impl Write) -> io::Result<()> {
+        let mnames = graph.marker_names();
+        let node_c = self.node_complexities();
+        let ranks = percentile_ranks(&node_c);
+        let columns = self.columns();
+        let mut markers : Vec<(&String,Marker)> = node_c.keys().map(|m| (&mnames[m],*m)).collect();
+        markers.sort();
+        write_table_header(out, self.unit, &self.contextlens, &self.labels())?;
+        for (name,m) in markers {
+            write_table_row(out, graph, name, m, &format!("{:.4}",ranks[&m]), &self.values(&columns, m))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the markers whose complexity lies between the lower and higher percentile, sorted by name.
+    /// With `TiePolicy::Exact`, ties are broken by name.
+    pub fn write_percentiles(&self, graph : &impl RearrangementGraph, lo : f64, hi : f64, ties : TiePolicy, out : &mut impl Write) -> io::Result<()> {
+        let mmap = graph.marker_names();
+        let columns = self.columns();
+        let named : HashMap<(&String,Marker),usize> = self.node_complexities().into_iter().map(|(m,c)| ((&mmap[&m],m),c)).collect();
+        let mut markers = Quantiles::new(&named).range(lo, hi, ties);
+        markers.sort();
+        writeln!(out,"#Context unit: {}",self.unit.name())?;
+        writeln!(out,"#Node\t{}",self.labels().join("\t"))?;
+        for (name,m) in markers {
+            writeln!(out,"{name}\t{}",self.values(&columns, m).join("\t"))?;
+        }
+        Ok(())
+    }
+
+    /// GFA tags of the scanned markers for the first context length: color, complexity and size of the neighborhood.
+    pub fn annotations(&self, colors : &ColorMap) -> HashMap<Marker,String> {
+        let mut annotations = HashMap::new();
+        for (marker,ns) in &self.scans[0].1 {
+            let n = &ns[0];
+            annotations.insert(*marker, format!("CL:z:{}\tcrp:i:{}\tnad:i:{}\tnmk:i:{}\tnsp:i:{}\tncr:f:{:.4}",colors.color(n.complexity),n.complexity,
+                n.adjacencies,n.markers,n.span,n.normalized()));
+        }
+        annotations
+    }
+
+    /// Adds the results of another scan of different markers of the same graph with the same parameters.
+    /// Fails if a marker was scanned in both, e.g. because the same shard is merged twice.
+    pub fn merge(&mut self, other : ScanResults) -> io::Result<()> {
+        let flanks = |r : &ScanResults| r.scans.iter().map(|(f,_)| *f).collect::<Vec<_>>();
+        if self.contextlens != other.contextlens || self.unit != other.unit || flanks(self) != flanks(&other) {
+            return Err(io::Error::other("Scan results to merge were calculated with different parameters."));
+        }
+        for ((_,mine),(_,theirs)) in self.scans.iter().zip(&other.scans) {
+            let shared = theirs.keys().filter(|m| mine.contains_key(m)).count();
+            if shared > 0 {
+                return Err(io::Error::other(format!("{shared} nodes are part of more than one of the scan results to merge.")));
+            }
+        }
+        for ((_,mine),(_,theirs)) in self.scans.iter_mut().zip(other.scans) {
+            mine.extend(theirs);
+        }
+        Ok(())
+    }
+}
+
+fn column_labels(columns : &[Column], contextlens : &[usize]) -> Vec<String> {
+    let mut labels = Vec::new();
+    for (label,_,_) in columns {
+        for c in contextlens {
+            if contextlens.len() == 1 {
+                labels.push(label.to_string());
+            } else {
+                labels.push(format!("{label} {c}"));
+            }
+        }
+    }
+    labels
+}
+
+fn write_table_header(out : &mut impl Write, unit : ContextUnit, contextlens : &[usize], labels : &[String]) -> io::Result<()> {
+    let contextlens : Vec<String> = contextlens.iter().map(|c| c.to_string()).collect();
+    writeln!(out,"#Context unit: {}",unit.name())?;
+    writeln!(out,"#Context length: {}",contextlens.join(","))?;
+    writeln!(out,"#Node\tSize\tHead degree\tTail degree\tPercentile\t{}",labels.join("\t"))
+}
+
+fn write_table_row(out : &mut impl Write, graph : &impl RearrangementGraph, name : &str, m : Marker, percentile : &str, values : &[String]) -> io::Result<()> {
+    let size = graph.node_size(m).map(|s| s.to_string()).unwrap_or(String::from("*"));
+    let hdeg = graph.degree(head(m)).unwrap_or(0);
+    let tdeg = graph.degree(tail(m)).unwrap_or(0);
+    writeln!(out,"{name}\t{size}\t{hdeg}\t{tdeg}\t{percentile}\t{}",values.join("\t"))
+}
+
+/// Writes the neighborhoods of markers in the format of `ScanResults::write_table` while they are scanned,
+/// e.g. from `scan_markers_streaming`. Rows are in the order in which they are written and the percentile
+/// ranks, which are only known after all markers are scanned, are written as `*`.
+pub struct TableStream<W : Write> {
+    out : W,
+    mnames : HashMap<Marker,String>,
+    columns : Vec<Column>
+}
+
+impl<W : Write> TableStream<W> {
+    pub fn new(graph : &impl RearrangementGraph, contextlens : &[usize], unit : ContextUnit, mut out : W) -> io::Result<Self> {
+        let columns : Vec<Column> = NEIGHBORHOOD_COLUMNS.iter().map(|(label,value)| (*label,0,*value)).collect();
+        write_table_header(&mut out, unit, contextlens, &column_labels(&columns, contextlens))?;
+        Ok(TableStream { out, mnames : graph.marker_names(), columns })
+    }
+
+    pub fn write(&mut self, graph : &impl RearrangementGraph, m : Marker, neighborhoods : &[NeighborhoodStats]) -> io::Result<()> {
+        let values : Vec<String> = self.columns.iter().flat_map(|(_,_,value)| neighborhoods.iter().map(value)).collect();
+        write_table_row(&mut self.out, graph, &self.mnames[&m], m, "*", &values)
+    }
+
+    /// Flushes the table and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Calls `f` with each row of a scan table and the complexity of the row for the first context length.
+fn for_table_rows<F : FnMut(&str,usize) -> io::Result<()>>(path : &str, mut f : F) -> io::Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        let complexity = line.split('\t').nth(5).and_then(|c| c.parse().ok())
+            .ok_or_else(|| io::Error::other(format!("Malformed scan table {path}: {line}")))?;
+        f(&line,complexity)?;
+    }
+    Ok(())
+}
+
+/// Histogram of the complexities for the first context length in a scan table, read without keeping the rows in memory.
+pub fn table_histogram(path : &str) -> io::Result<HashMap<usize,usize>> {
+    let mut hist = HashMap::new();
+    for_table_rows(path, |_,c| {
+        *hist.entry(c).or_insert(0) += 1;
+        Ok(())
+    })?;
+    Ok(hist)
+}
+
+/// Like `ScanResults::write_percentiles`, but reads the scan results from a table in two passes instead of
+/// keeping them in memory. Markers are output in the order of the table.
+pub fn write_table_percentiles(path : &str, lo : f64, hi : f64, ties : TiePolicy, out : &mut impl Write) -> io::Result<()> {
+    let hist = table_histogram(path)?;
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(unit) = line.strip_prefix("#Context unit: ") {
+            writeln!(out,"#Context unit: {unit}")?;
+        } else if line.starts_with("#Node") {
+            let labels : Vec<&str> = line.split('\t').skip(5).collect();
+            writeln!(out,"#Node\t{}",labels.join("\t"))?;
+            break;
+        }
+    }
+    let Some((lt,ht)) = quantile_thresholds(&hist, lo, hi, ties) else {
+        return Ok(());
+    };
+    let row = |line : &str| {
+        let fields : Vec<&str> = line.split('\t').collect();
+        format!("{}\t{}",fields[0],fields[5..].join("\t"))
+    };
+    if ties != TiePolicy::Exact {
+        return for_table_rows(path, |line,c| {
+            if c >= lt && c < ht {
+                writeln!(out,"{}",row(line))?;
+            }
+            Ok(())
+        });
+    }
+    //ties are broken by name, so the rows of the boundary complexities are only known after reading all of them
+    let mut candidates : Vec<(usize,String,String)> = Vec::new();
+    for_table_rows(path, |line,c| {
+        if c >= lt && c < ht {
+            candidates.push((c,line.split('\t').next().unwrap_or_default().to_string(),row(line)));
+        }
+        Ok(())
+    })?;
+    let lower : usize = hist.iter().filter(|(c,_)| **c < lt).map(|(_,n)| n).sum();
+    let (s,e) = rank_positions(hist.values().sum(), lo, hi);
+    let mut order : Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by(|i,j| (candidates[*i].0,&candidates[*i].1).cmp(&(candidates[*j].0,&candidates[*j].1)));
+    let mut selected = vec![false;candidates.len()];
+    for (pos,i) in order.into_iter().enumerate() {
+        selected[i] = lower+pos >= s && lower+pos < e;
+    }
+    for ((_,_,line),is_selected) in candidates.iter().zip(selected) {
+        if is_selected {
+            writeln!(out,"{line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads scan results written by `ScanResults::write_table`. Node names are resolved in `graph`.
+pub fn read_scan_results(graph : &impl RearrangementGraph, path : &str) -> io::Result<ScanResults> {
+    let reader = BufReader::new(File::open(path)?);
+    let malformed = |what : &str| io::Error::other(format!("Malformed scan table {path}: {what}"));
+    let mut unit = None;
+    let mut contextlens = None;
+    let mut groups = None;
+    let mut scans : Vec<(Flank,HashMap<Marker,Vec<NeighborhoodStats>>)> = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(u) = line.strip_prefix("#Context unit: ") {
+            unit = match u {
+                "bp" => Some(ContextUnit::BasePairs),
+                "nodes" => Some(ContextUnit::Nodes),
+                _ => return Err(malformed(&line))
+            };
+            continue;
+        }
+        if let Some(cs) = line.strip_prefix("#Context length: ") {
+            let cs : Result<Vec<usize>,_> = cs.split(',').map(|c| c.parse()).collect();
+            contextlens = Some(cs.map_err(|_| malformed(&line))?);
+            continue;
+        }
+        if line.starts_with("#Node") {
+            let k = contextlens.as_ref().ok_or_else(|| malformed("missing context lengths"))?.len();
+            let n_values = line.split('\t').count()-5;
+            groups = Some(n_values/k);
+            scans = match n_values/k {
+                5 => vec![(Flank::Both,HashMap::new())],
+                7 => vec![(Flank::Both,HashMap::new()),(Flank::Head,HashMap::new()),(Flank::Tail,HashMap::new())],
+                _ => return Err(malformed(&line))
+            };
+            continue;
+        }
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        let (k,n_groups) = match (&contextlens,groups) {
+            (Some(cs),Some(g)) => (cs.len(),g),
+            (_,_) => return Err(malformed("missing header"))
+        };
+        let fields : Vec<&str> = line.split('\t').collect();
+        if fields.len() != 5+k*n_groups {
+            return Err(malformed(&line));
+        }
+        let m = graph.name_to_marker(fields[0])
+            .ok_or_else(|| io::Error::other(format!("Node {} of {path} is not part of the (trimmed) graph.",fields[0])))?;
+        let value = |group : usize, c : usize| -> io::Result<usize> {
+            fields[5+group*k+c].parse().map_err(|_| malformed(&line))
+        };
+        let mut neighborhoods = Vec::new();
+        for c in 0..k {
+            neighborhoods.push(NeighborhoodStats {
+                complexity : value(0,c)?,
+                adjacencies : value(1,c)?,
+                markers : value(2,c)?,
+                span : value(3,c)?
+            });
+        }
+        scans[0].1.insert(m, neighborhoods);
+        for (i,group) in [(1,5),(2,6)] {
+            if i < scans.len() {
+                let flank = (0..k).map(|c| Ok(NeighborhoodStats { complexity : value(group,c)?, ..Default::default() })).collect::<io::Result<Vec<_>>>()?;
+                scans[i].1.insert(m, flank);
+            }
+        }
+    }
+    match (contextlens,unit) {
+        (Some(contextlens),Some(unit)) if !scans.is_empty() => Ok(ScanResults { contextlens, unit, scans }),
+        (_,_) => Err(malformed("missing header"))
+    }
+}
+
+/// Writes the color, complexity and normalized complexity of the scanned markers as CSV, sorted by node name.
+/// The file can be loaded in Bandage on top of the original gfa.
+pub fn write_bandage_csv(graph : &impl RearrangementGraph, results : &ScanResults, colors : &ColorMap, out : &mut impl Write) -> io::Result<()> {
+    let mnames = graph.marker_names();
+    let mut markers : Vec<(&String,&Vec<NeighborhoodStats>)> = results.scans[0].1.iter().map(|(m,ns)| (&mnames[m],ns)).collect();
+    markers.sort_by(|a,b| a.0.cmp(b.0));
+    writeln!(out,"Name,Color,SCJ-CARP-measure,Normalized SCJ-CARP-measure")?;
+    for (name,ns) in markers {
+        let name = if name.contains([',','"']) { format!("\"{}\"",name.replace('"',"\"\"")) } else { name.clone() };
+        writeln!(out,"{name},{},{},{:.4}",colors.color(ns[0].complexity),ns[0].complexity,ns[0].normalized())?;
+    }
+    Ok(())
+}
+
+/// Copies the gfa file at `path` to `out`, appending the annotation of each segment that is a scanned
+/// marker to its line. Tags of the segment with the same names as the annotation are replaced, all other
+/// lines are copied unchanged. Returns the number of annotated segments.
+pub fn annotate_gfa(graph : &impl RearrangementGraph, annotations : &HashMap<Marker,String>, path : &str, out : &mut impl Write) -> io::Result<usize> {
+    let reader = BufReader::new(open_gfa(path)?);
+    let mut annotated = 0;
+    for line in reader.lines() {
+        let line = line?;
+        let annotation = match line.strip_prefix("S\t") {
+            Some(rest) => graph.name_to_marker(rest.split('\t').next().unwrap_or(""))
+                .and_then(|m| annotations.get(&m)),
+            None => None
+        };
+        let Some(annotation) = annotation else {
+            writeln!(out,"{line}")?;
+            continue;
+        };
+        let tag_name = |t : &str| t.split_once(':').map(|(n,_)| n.to_string());
+        let tag_names : Vec<String> = annotation.split('\t').filter_map(tag_name).collect();
+        //name and sequence are never tags
+        let fields : Vec<&str> = line.split('\t').enumerate()
+            .filter(|(i,f)| *i < 3 || !tag_name(f).is_some_and(|n| tag_names.contains(&n)))
+            .map(|(_,f)| f)
+            .collect();
+        writeln!(out,"{}\t{annotation}",fields.join("\t"))?;
+        annotated += 1;
+    }
+    Ok(annotated)
+}
+
+pub fn to_gfa_annotated(graph : &impl RearrangementGraph, annotations : &HashMap<Marker,String>, file : &str) -> std::io::Result<()>
+{
+    let mut file = BufWriter::new(File::create(file)?);
+    let mnames = graph.marker_names();
+    for (mid,mname) in &mnames {
+        write!(file,"S\t{mname}\t*")?;
+        if let Some(msiz) = graph.node_size(*mid) {
+            write!(file,"\tLN:i:{msiz}")?;
+        }
+        if let Some(t) = annotations.get(mid) {
+            write!(file,"\t{t}")?;
+        }
+        writeln!(file)?;
+    }
+    for (x,y) in graph.iter_adjacencies() {
+        let m1n = marker(x);
+        let m2n = marker(y);
+        if let Some(m1) = mnames.get(&m1n) {
+            if let Some(m2) = mnames.get(&m2n) {
+                let orient1 = if is_tail(x) {
+                    "-"
+                } else {
+                    "+"
+                };
+                let orient2 = if is_tail(y) {
+                    "+"
+                } else {
+                    "-"
+                };
+
+                writeln!(file,"L\t{m1}\t{orient1}\t{m2}\t{orient2}\t0M")?;
+            }
+        }
+    }
+    file.flush()
+}
+
+/// Writes the graph annotated with the scan results to `path`, together with the legend of the colors
+/// to the same path with extension `.legend.svg`.
+pub fn write_colored_gfa(graph : &impl RearrangementGraph, results : &ScanResults, colors : &ColorMap, path : &str) -> io::Result<()> {
+    to_gfa_annotated(graph, &results.annotations(colors), path)?;
+    let mut legend = File::create(Path::new(path).with_extension("legend.svg"))?;
+    let title = match results.contextlens.first() {
+        Some(c) => format!("SCJ-CARP-measure ({c} {})",results.unit.name()),
+        None => String::from("SCJ-CARP-measure")
+    };
+    colors.write_legend_svg(&title, &mut legend)
+}
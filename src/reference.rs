@@ -0,0 +1,100 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use crate::rearrangement::*;
+use crate::gfa::ReferencePath;
+use crate::hotspot::Hotspot;
+
+/// Half-open coordinates `[start,end)` of one step of a reference path.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct ReferenceInterval {
+    pub start : usize,
+    pub end : usize,
+    /// Marker of the step, `None` if the segment is not part of the (trimmed) graph.
+    pub marker : Option<Marker>
+}
+
+/// Reference path with the coordinates of its steps.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ProjectedPath {
+    pub sequence : String,
+    pub intervals : Vec<ReferenceInterval>
+}
+
+/// Walks the reference paths and accumulates the lengths of their segments to coordinates. The overlap
+/// of consecutive segments is assigned to the latter one, so the intervals of a path do not overlap.
+pub fn project_reference(graph : &impl RearrangementGraph, paths : &[ReferencePath], lengths : &HashMap<String,usize>) -> io::Result<Vec<ProjectedPath>> {
+    let mut projected = Vec::new();
+    for path in paths {
+        let mut intervals : Vec<ReferenceInterval> = Vec::with_capacity(path.steps.len());
+        let mut pos = path.start;
+        let mut last : Option<(bool,Marker)> = None;
+        for (name,is_forward) in &path.steps {
+            let len = *lengths.get(name)
+                .ok_or_else(|| io::Error::other(format!("Segment {name} of reference sequence {} has no length.",path.sequence)))?;
+            let marker = graph.name_to_marker(name);
+            if let (Some(prev),Some(m),Some(prev_interval)) = (last,marker,intervals.last_mut()) {
+                let (x,y) = to_adjacency(prev,(*is_forward,m));
+                let overlap = graph.overlap(x, y).min(prev_interval.end-prev_interval.start);
+                prev_interval.end -= overlap;
+                pos -= overlap;
+            }
+            intervals.push(ReferenceInterval { start : pos, end : pos+len, marker });
+            pos += len;
+            last = marker.map(|m| (*is_forward,m));
+        }
+        projected.push(ProjectedPath { sequence : path.sequence.clone(), intervals });
+    }
+    Ok(projected)
+}
+
+/// Writes the complexity of each step of the reference paths as bedGraph, sorted by sequence and start.
+/// Steps through markers without complexity, e.g. trimmed or unscanned ones, are left out.
+pub fn write_bedgraph(projected : &[ProjectedPath], node_complexities : &HashMap<Marker,usize>, out : &mut impl Write) -> io::Result<()> {
+    writeln!(out,"track type=bedGraph name=\"SCJ-CARP-measure\"")?;
+    let mut records = Vec::new();
+    for path in projected {
+        for interval in &path.intervals {
+            let Some(c) = interval.marker.and_then(|m| node_complexities.get(&m)) else {
+                continue;
+            };
+            if interval.end > interval.start {
+                records.push((&path.sequence,interval.start,interval.end,*c));
+            }
+        }
+    }
+    records.sort();
+    for (sequence,start,end,c) in records {
+        writeln!(out,"{sequence}\t{start}\t{end}\t{c}")?;
+    }
+    Ok(())
+}
+
+/// Writes the reference intervals covered by each hotspot as BED, named `hotspot_<i>` after the index of the
+/// hotspot in `hotspots`, sorted by sequence and start. Intervals of the same hotspot on the same path that are
+/// at most `max_gap` apart are merged.
+pub fn write_hotspot_bed(projected : &[ProjectedPath], hotspots : &[Hotspot], max_gap : usize, out : &mut impl Write) -> io::Result<()> {
+    let hotspot_of : HashMap<Marker,usize> = hotspots.iter().enumerate()
+        .flat_map(|(i,h)| h.markers.iter().map(move |m| (*m,i)))
+        .collect();
+    let mut records : Vec<(&String,usize,usize,usize)> = Vec::new();
+    for path in projected {
+        let mut open : BTreeMap<usize,(usize,usize)> = BTreeMap::new();
+        for interval in &path.intervals {
+            let Some(h) = interval.marker.and_then(|m| hotspot_of.get(&m)) else {
+                continue;
+            };
+            match open.get_mut(h) {
+                Some((_,end)) if interval.start <= *end+max_gap => *end = (*end).max(interval.end),
+                _ => if let Some((start,end)) = open.insert(*h,(interval.start,interval.end)) {
+                    records.push((&path.sequence,start,end,*h));
+                }
+            }
+        }
+        records.extend(open.into_iter().map(|(h,(start,end))| (&path.sequence,start,end,h)));
+    }
+    records.sort();
+    for (sequence,start,end,h) in records {
+        writeln!(out,"{sequence}\t{start}\t{end}\thotspot_{h}")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::collections::hash_map::Entry;
+use std::io::{self, Write};
+use crate::rearrangement::*;
+use crate::scan::*;
+use crate::util::*;
+
+/// Number of anchor markers to sample, either absolute or as fraction of all markers.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum SampleSize {
+    Count(usize),
+    Fraction(f64)
+}
+
+impl SampleSize {
+    pub fn resolve(&self, n_markers : usize) -> usize {
+        match self {
+            SampleSize::Count(n) => (*n).min(n_markers),
+            SampleSize::Fraction(f) => ((f.clamp(0.0,1.0)*n_markers as f64).round() as usize).min(n_markers)
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SampleWeighting {
+    /// Every marker is equally likely to be sampled.
+    Uniform,
+    /// Markers are sampled with probability proportional to their node size.
+    Length
+}
+
+impl SampleWeighting {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SampleWeighting::Uniform => "uniform",
+            SampleWeighting::Length => "length"
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct SamplingParams {
+    pub size : SampleSize,
+    /// Unit of the context lengths and of the distances to anchors.
+    pub unit : ContextUnit,
+    pub weighting : SampleWeighting,
+    pub seed : u64
+}
+
+/// Result of a sampled scan. Anchors have their exact neighborhood statistics, all other markers the
+/// ones of their nearest anchor.
+#[derive(Debug,Clone)]
+pub struct SampledScan {
+    /// Neighborhood statistics of each marker, one per context length.
+    pub neighborhoods : HashMap<Marker,Vec<NeighborhoodStats>>,
+    /// Sampled anchors followed by the anchors added for components.
+    pub anchors : Vec<Marker>,
+    /// Nearest anchor of each marker, anchors map to themselves.
+    pub anchor_of : HashMap<Marker,Marker>,
+    /// Graph distance of each marker to its nearest anchor.
+    pub anchor_distance : HashMap<Marker,usize>,
+    /// Number of sampled anchors.
+    pub sampled : usize,
+    /// Number of anchors added for connected components without sampled anchor.
+    pub component_anchors : usize
+}
+
+/// Samples `n` distinct markers without replacement (Efraimidis-Spirakis), weighted according to `weighting`.
+pub fn sample_anchors(graph : &impl RearrangementGraph, n : usize, weighting : SampleWeighting, seed : u64) -> Vec<Marker> {
+    let mut rng = SplitMix64::new(seed);
+    let mut keyed : Vec<(f64,Marker)> = scannable_markers(graph).into_iter().map(|m| {
+        let weight = match weighting {
+            SampleWeighting::Uniform => 1.0,
+            SampleWeighting::Length => graph.node_size(m).unwrap_or(1).max(1) as f64
+        };
+        ((1.0-rng.next_f64()).ln()/weight,m)
+    }).collect();
+    keyed.sort_by(|a,b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+    let mut anchors : Vec<Marker> = keyed.into_iter().take(n).map(|(_,m)| m).collect();
+    anchors.sort();
+    anchors
+}
+
+struct AnchorSearch {
+    dist : Vec<usize>,
+    label : Vec<Marker>,
+    heap : BinaryHeap<State>,
+    anchor_of : HashMap<Marker,Marker>,
+    anchor_distance : HashMap<Marker,usize>
+}
+
+impl AnchorSearch {
+    fn new(graph : &impl RearrangementGraph) -> Self {
+        let n = graph.markers().max().map(head).unwrap_or(0)+1;
+        AnchorSearch {
+            dist : vec![usize::MAX;n],
+            label : vec![0;n],
+            heap : BinaryHeap::new(),
+            anchor_of : HashMap::new(),
+            anchor_distance : HashMap::new()
+        }
+    }
+
+    fn seed(&mut self, a : Marker) {
+        for x in [head(a),tail(a)] {
+            self.dist[x] = 0;
+            self.label[x] = a;
+            self.heap.push(State { cost : 0, position : x });
+        }
+    }
+
+    /// Multi-source Dijkstra from the seeded anchors, using the same distances as `adjacency_neighborhood_reach`.
+    fn run(&mut self, unit : ContextUnit, graph : &impl RearrangementGraph) {
+        while let Some(State {cost, position}) = self.heap.pop() {
+            if cost > self.dist[position] {
+                continue;
+            }
+            let m = marker(position);
+            let a = self.label[position];
+            if let Entry::Vacant(e) = self.anchor_distance.entry(m) {
+                e.insert(cost);
+                self.anchor_of.insert(m, a);
+            }
+            let Some(neighbors) = graph.adj_neighbors(position) else {
+                continue;
+            };
+            for neighbor in neighbors {
+                if neighbor == TELOMERE {
+                    continue;
+                }
+                let oend = other(neighbor);
+                let ndist = cost + unit.step(position, neighbor, graph);
+                if self.dist[oend] > ndist {
+                    self.dist[oend] = ndist;
+                    self.label[oend] = a;
+                    self.heap.push(State { cost : ndist, position : oend });
+                }
+            }
+        }
+    }
+}
+
+/// Approximates `scan_graph_multiscale` by calculating the neighborhoods only for a sample of
+/// anchor markers and assigning every other marker the neighborhood statistics of its nearest anchor.
+/// Connected components that contain no sampled anchor get their smallest marker as anchor.
+pub fn sample_scan(graph : &impl RearrangementGraph, depths : &[usize], params : &SamplingParams, n_threads : usize) -> SampledScan {
+    let markers = scannable_markers(graph);
+    let n = params.size.resolve(markers.len());
+    let mut anchors = sample_anchors(graph, n, params.weighting, params.seed);
+    let sampled = anchors.len();
+    log_info!("Sampled {sampled} of {} markers as anchors.",markers.len());
+    let mut search = AnchorSearch::new(graph);
+    for a in &anchors {
+        search.seed(*a);
+    }
+    search.run(params.unit, graph);
+    for m in &markers {
+        if !search.anchor_of.contains_key(m) {
+            anchors.push(*m);
+            search.seed(*m);
+            search.run(params.unit, graph);
+        }
+    }
+    let component_anchors = anchors.len()-sampled;
+    if component_anchors > 0 {
+        log_info!("Added {component_anchors} anchors for components without sampled anchor.");
+    }
+    let mut scan = SampledScan {
+        neighborhoods : HashMap::new(),
+        anchors,
+        anchor_of : search.anchor_of,
+        anchor_distance : search.anchor_distance,
+        sampled,
+        component_anchors
+    };
+    scan.neighborhoods = scan.rescan(graph, depths, params.unit, Flank::Both, n_threads);
+    scan
+}
+
+impl SampledScan {
+    /// Calculates neighborhood statistics for the same anchors with different parameters, e.g. for
+    /// another flank, and assigns them to the markers like the original ones.
+    pub fn rescan(&self, graph : &impl RearrangementGraph, depths : &[usize], unit : ContextUnit, flank : Flank, n_threads : usize) -> HashMap<Marker,Vec<NeighborhoodStats>> {
+        let anchor_stats = scan_markers_multithread(graph, depths, unit, flank, &self.anchors, n_threads);
+        self.anchor_of.iter()
+            .map(|(m,a)| (*m,anchor_stats[a].clone()))
+            .collect()
+    }
+}
+
+/// Writes the sampling parameters and statistics of a sampled scan as tab separated table, followed by
+/// a table of the anchor of each node and its distance to it, sorted by node name.
+pub fn write_sampling_report(graph : &impl RearrangementGraph, params : &SamplingParams, depths : &[usize], scan : &SampledScan, out : &mut impl Write) -> io::Result<()> {
+    let requested = match params.size {
+        SampleSize::Count(n) => n.to_string(),
+        SampleSize::Fraction(f) => format!("{f}")
+    };
+    let max_distance = scan.anchor_distance.values().max().copied().unwrap_or(0);
+    let mean_distance = if scan.anchor_distance.is_empty() {
+        0.0
+    } else {
+        scan.anchor_distance.values().sum::<usize>() as f64 / scan.anchor_distance.len() as f64
+    };
+    writeln!(out,"#Parameter\tValue")?;
+    writeln!(out,"Markers\t{}",scan.neighborhoods.len())?;
+    writeln!(out,"Requested sample size\t{requested}")?;
+    writeln!(out,"Weighting\t{}",params.weighting.name())?;
+    writeln!(out,"Seed\t{}",params.seed)?;
+    let depths = depths.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(",");
+    writeln!(out,"Context length\t{depths}")?;
+    writeln!(out,"Context unit\t{}",params.unit.name())?;
+    writeln!(out,"Sampled anchors\t{}",scan.sampled)?;
+    writeln!(out,"Component anchors\t{}",scan.component_anchors)?;
+    writeln!(out,"Max anchor distance\t{max_distance}")?;
+    writeln!(out,"Mean anchor distance\t{mean_distance:.2}")?;
+    let names = graph.marker_names();
+    let mut anchors : Vec<(&String,&String,usize)> = scan.anchor_of.iter()
+        .map(|(m,a)| (&names[m],&names[a],scan.anchor_distance[m]))
+        .collect();
+    anchors.sort();
+    writeln!(out,"#Node\tAnchor\tDistance")?;
+    for (name,anchor,distance) in anchors {
+        writeln!(out,"{name}\t{anchor}\t{distance}")?;
+    }
+    Ok(())
+}
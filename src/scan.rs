@@ -1,14 +1,26 @@
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, BufRead, BufReader};
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::thread;
 use crate::rearrangement::*;
 use crate::measure::*;
 use crate::util::*;
 
+/// Number of marker ids a thread processes at once when scanning. Neighborhoods in complex
+/// regions are much more expensive than elsewhere, so chunks are kept small.
+pub const SCAN_CHUNK_SIZE : usize = 64;
+
+/// Number of scanned chunks that may wait for the consumer of a streaming scan before the scanning threads block.
+pub const STREAM_BUFFER : usize = 256;
+
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct State {
-    cost: usize,
-    position: Extremity,
+pub(crate) struct State {
+    pub(crate) cost: usize,
+    pub(crate) position: Extremity,
 }
 
 // The priority queue depends on `Ord`.
@@ -31,16 +43,87 @@ impl PartialOrd for State {
     }
 }
 
+/// Unit in which context lengths are measured.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ContextUnit {
+    /// Base pairs, i.e. node sizes minus overlaps. Nodes without size (e.g. from unimog files) count as one base pair.
+    BasePairs,
+    /// Number of nodes, regardless of their size.
+    Nodes
+}
+
+impl ContextUnit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContextUnit::BasePairs => "bp",
+            ContextUnit::Nodes => "nodes"
+        }
+    }
+
+    /// Distance of the extremities of `m` from the center of `m`.
+    pub fn init_dist(&self, m : Marker, graph : &impl RearrangementGraph) -> usize {
+        match self {
+            ContextUnit::BasePairs => graph.node_size(m).unwrap_or(0)/2,
+            ContextUnit::Nodes => 0
+        }
+    }
+
+    /// Length of the step from `position` over the adjacency to `neighbor` and through its marker.
+    pub fn step(&self, position : Extremity, neighbor : Extremity, graph : &impl RearrangementGraph) -> usize {
+        match self {
+            ContextUnit::BasePairs => graph.node_size(marker(neighbor)).unwrap_or(1) - graph.overlap(position, neighbor),
+            ContextUnit::Nodes => 1
+        }
+    }
+}
+
+/// Side of a marker from which its neighborhood is explored.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Flank {
+    Both,
+    /// Only the neighborhood reached via the head of the marker.
+    Head,
+    /// Only the neighborhood reached via the tail of the marker.
+    Tail
+}
+
+impl Flank {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flank::Both => "both",
+            Flank::Head => "head",
+            Flank::Tail => "tail"
+        }
+    }
+
+    /// Extremities of `m` at which the exploration of its neighborhood starts.
+    pub fn seeds(&self, m : Marker) -> Vec<Extremity> {
+        match self {
+            Flank::Both => vec![head(m),tail(m)],
+            Flank::Head => vec![head(m)],
+            Flank::Tail => vec![tail(m)]
+        }
+    }
+}
+
 pub fn adjacency_neighborhood(m : Marker,max_depth : usize, graph : &impl RearrangementGraph) -> HashSet<Adjacency>{
+    adjacency_neighborhood_reach(m, max_depth, ContextUnit::BasePairs, Flank::Both, graph).into_keys().collect()
+}
+
+/// Like `adjacency_neighborhood`, but additionally records for each adjacency the distance at which
+/// it is reached, i.e. the adjacency is part of the neighborhood of `m` for all context lengths
+/// of at least that distance.
+pub fn adjacency_neighborhood_reach(m : Marker,max_depth : usize, unit : ContextUnit, flank : Flank, graph : &impl RearrangementGraph) -> HashMap<Adjacency,usize>{
     let mut visited = BinaryHeap::new();
-    let init_dist = graph.node_size(m).unwrap_or(0)/2;
-    let mut adjacencies : HashSet<Adjacency> = HashSet::new();
+    let init_dist = unit.init_dist(m, graph);
+    let mut adjacencies : HashMap<Adjacency,usize> = HashMap::new();
     let mut min_dist = HashMap::new();
     if init_dist > max_depth {
         return  adjacencies;
     }
-    visited.push(State {cost : init_dist,position : head(m)});
-    visited.push(State {cost : init_dist,position : tail(m)});
+    for x in flank.seeds(m) {
+        visited.push(State {cost : init_dist,position : x});
+    }
     while let Some(State {cost, position}) = visited.pop(){
         if let Some(neighbors) = graph.adj_neighbors(position) {
             for neigbor in neighbors {
@@ -49,7 +132,8 @@ pub fn adjacency_neighborhood(m : Marker,max_depth : usize, graph : &impl Rearra
                     } else {
                         (neigbor,position)
                     };
-                adjacencies.insert(adj);
+                let reach = adjacencies.entry(adj).or_insert(cost);
+                *reach = cost.min(*reach);
                 if neigbor == TELOMERE {
                     //skip telomeres, they're not real connections
                     continue;
@@ -57,7 +141,7 @@ pub fn adjacency_neighborhood(m : Marker,max_depth : usize, graph : &impl Rearra
                 let oend = other(neigbor);
                 
                 
-                let ndist: usize = cost + graph.node_size(marker(neigbor)).unwrap_or(1) - graph.overlap(position, neigbor);//.node_sizes.get(&marker(*neigbor)).unwrap_or(&1);
+                let ndist: usize = cost + unit.step(position, neigbor, graph);
                 if ndist <= max_depth && *min_dist.get(&oend).unwrap_or(&(ndist+1)) > ndist {
                     visited.push(State{cost: ndist,position: oend});
                     min_dist.insert(oend, ndist);
@@ -68,6 +152,263 @@ pub fn adjacency_neighborhood(m : Marker,max_depth : usize, graph : &impl Rearra
     adjacencies
 }
 
+/// Complexity of the neighborhood of a marker together with the size of the neighborhood.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub struct NeighborhoodStats {
+    /// Number of contested adjacencies.
+    pub complexity : usize,
+    /// Number of non-telomeric adjacencies.
+    pub adjacencies : usize,
+    pub markers : usize,
+    /// Sum of the sizes of the markers, markers without size count as one base pair.
+    pub span : usize
+}
+
+impl NeighborhoodStats {
+    /// Fraction of the non-telomeric adjacencies that are contested.
+    pub fn normalized(&self) -> f64 {
+        if self.adjacencies == 0 {
+            return 0.0;
+        }
+        self.complexity as f64 / self.adjacencies as f64
+    }
+}
+
+/// Reusable buffers for calculating the complexity of the neighborhoods of many markers in a row.
+/// The buffers are indexed by extremity and only the entries touched by the previous marker are
+/// reset, so consecutive markers neither allocate nor hash. Calculates the same values as
+/// `carp_measure_from_adjacencies(&adjacency_neighborhood(..))`.
+///
+/// Every marker is explored only once, for the largest context length. The exploration
+/// determines for each adjacency the context length from which on it is contested, so the
+/// neighborhood statistics for any number of context lengths are answered by binary searches.
+///
+/// Instead of recounting degrees from the adjacencies of each neighborhood, the workspace looks
+/// them up in a table of the degrees of all extremities in the graph that is computed once and
+/// shared by all clones of the workspace. All adjacencies of an extremity that is settled by the
+/// exploration belong to the neighborhood, so its degree in the neighborhood is its global degree.
+#[derive(Clone)]
+pub struct ScanWorkspace {
+    //number of distinct adjacencies of each extremity in the whole graph, self loops count twice
+    degrees : Arc<[u32]>,
+    dist : Vec<usize>,
+    settled : Vec<bool>,
+    //extremities already counted as part of a neighborhood marker
+    counted : Vec<bool>,
+    touched : Vec<Extremity>,
+    heap : BinaryHeap<State>,
+    //adjacencies of the neighborhood with the distance at which they are reached and the
+    //extremity that was settled when they were collected
+    adjacencies : Vec<(Adjacency,usize,Extremity)>,
+    //sorted distances from which on the adjacencies are contested
+    contested : Vec<usize>,
+    //sorted distances at which the non-telomeric adjacencies are reached
+    linked : Vec<usize>,
+    //distances at which the markers of the neighborhood are reached, with the total span up to them
+    spans : Vec<(usize,usize)>,
+    unit : ContextUnit
+}
+
+/// Number of distinct adjacencies of every extremity of `graph`, indexed by extremity. As in
+/// `carp_measure_from_adjacencies`, a self loop counts twice.
+pub fn extremity_degrees(graph : &impl RearrangementGraph) -> Vec<u32> {
+    let n = graph.markers().max().map(head).unwrap_or(0)+1;
+    let mut degrees = vec![0;n];
+    let mut neighbors = Vec::new();
+    for m in graph.markers() {
+        for x in [tail(m),head(m)] {
+            neighbors.clear();
+            neighbors.extend(graph.adj_neighbors(x).into_iter().flatten());
+            neighbors.sort_unstable();
+            neighbors.dedup();
+            let self_loop = neighbors.binary_search(&x).is_ok();
+            degrees[x] = (neighbors.len()+usize::from(self_loop)) as u32;
+        }
+    }
+    degrees
+}
+
+impl ScanWorkspace {
+    /// Creates a workspace for `graph`, computing its degree table. Workspaces for further
+    /// threads should be cloned from this one to share the table.
+    pub fn new(graph : &impl RearrangementGraph, unit : ContextUnit) -> Self {
+        let degrees : Arc<[u32]> = extremity_degrees(graph).into();
+        let n = degrees.len();
+        ScanWorkspace {
+            unit,
+            degrees,
+            dist : vec![usize::MAX;n],
+            settled : vec![false;n],
+            counted : vec![false;n],
+            touched : Vec::new(),
+            heap : BinaryHeap::new(),
+            adjacencies : Vec::new(),
+            contested : Vec::new(),
+            linked : Vec::new(),
+            spans : Vec::new()
+        }
+    }
+
+    fn reset(&mut self) {
+        for x in self.touched.drain(..) {
+            self.dist[x] = usize::MAX;
+            self.settled[x] = false;
+        }
+        for ((x,y),_,_) in self.adjacencies.drain(..) {
+            self.counted[x] = false;
+            self.counted[y] = false;
+        }
+        self.contested.clear();
+        self.linked.clear();
+        self.spans.clear();
+        self.heap.clear();
+    }
+
+    fn reach(&mut self, x : Extremity, dist : usize) {
+        self.dist[x] = dist;
+        self.touched.push(x);
+        self.heap.push(State { cost : dist, position : x });
+    }
+
+    /// Collects the adjacencies of the neighborhood of `m` like `adjacency_neighborhood`, but
+    /// settles every extremity only once. Afterwards the adjacencies are sorted by the distance at
+    /// which they are reached and `profile` has summarized them.
+    fn expand(&mut self, m : Marker, max_depth : usize, flank : Flank, graph : &impl RearrangementGraph) {
+        self.reset();
+        let init_dist = self.unit.init_dist(m, graph);
+        if init_dist > max_depth {
+            return;
+        }
+        for x in flank.seeds(m) {
+            self.reach(x, init_dist);
+        }
+        while let Some(State {cost, position}) = self.heap.pop() {
+            if self.settled[position] || cost > self.dist[position] {
+                continue;
+            }
+            self.settled[position] = true;
+            let Some(neighbors) = graph.adj_neighbors(position) else {
+                continue;
+            };
+            for neighbor in neighbors {
+                //otherwise the adjacency was already collected when the neighbor was settled
+                if neighbor == position || !self.settled[neighbor] {
+                    self.adjacencies.push((canonicize((position,neighbor)),cost,position));
+                }
+                if neighbor == TELOMERE {
+                    continue;
+                }
+                let oend = other(neighbor);
+                let ndist: usize = cost + self.unit.step(position, neighbor, graph);
+                if ndist <= max_depth && self.dist[oend] > ndist {
+                    self.reach(oend, ndist);
+                }
+            }
+        }
+        //self loops and parallel edges are listed more than once, but only count once
+        self.adjacencies.sort_unstable();
+        self.adjacencies.dedup_by_key(|(adj,_,_)| *adj);
+        self.adjacencies.sort_by_key(|(_,reach,_)| *reach);
+        self.profile(graph);
+    }
+
+    /// Second smallest distance at which the adjacencies of the extremity `x` are reached, where
+    /// `x` has at least two adjacencies. An adjacency is reached when the first of its
+    /// extremities is settled, and all adjacencies that do not lead to another settled
+    /// extremity are reached when `x` itself is settled.
+    fn second_reach(&self, x : Extremity, graph : &impl RearrangementGraph) -> usize {
+        let own = if self.settled[x] { self.dist[x] } else { usize::MAX };
+        let mut first = (usize::MAX,TELOMERE);
+        let mut second = usize::MAX;
+        for y in graph.adj_neighbors(x).into_iter().flatten() {
+            //parallel edges are listed more than once
+            if y == x || y == TELOMERE || y == first.1 || !self.settled[y] {
+                continue;
+            }
+            if self.dist[y] < first.0 {
+                second = first.0;
+                first = (self.dist[y],y);
+            } else if self.dist[y] < second {
+                second = self.dist[y];
+            }
+        }
+        second.min(own)
+    }
+
+    /// An adjacency is contested as soon as one of its extremities is part of a second
+    /// neighborhood adjacency. If the extremity that collected the adjacency has further
+    /// adjacencies, they have all been reached by then. Otherwise the adjacency is contested
+    /// once the second adjacency of its other extremity is reached.
+    fn profile(&mut self, graph : &impl RearrangementGraph) {
+        let mut span = 0;
+        for k in 0..self.adjacencies.len() {
+            let ((x,y),reach,collector) = self.adjacencies[k];
+            if x == TELOMERE || y == TELOMERE {
+                continue;
+            }
+            self.linked.push(reach);
+            let oend = if collector == x { y } else { x };
+            let threshold = if self.degrees[collector] > 1 {
+                reach
+            } else if self.degrees[oend] > 1 {
+                reach.max(self.second_reach(oend, graph))
+            } else {
+                usize::MAX
+            };
+            if threshold != usize::MAX {
+                self.contested.push(threshold);
+            }
+            for z in [x,y] {
+                if self.counted[z] {
+                    continue;
+                }
+                self.counted[z] = true;
+                if !self.counted[other(z)] {
+                    span+=graph.node_size(marker(z)).unwrap_or(1);
+                    self.spans.push((reach,span));
+                }
+            }
+        }
+        self.contested.sort_unstable();
+    }
+
+    /// Complexity of the adjacencies reached within `depth`, `expand` has to be called with a
+    /// context length of at least `depth` before.
+    fn complexity_within(&self, depth : usize) -> usize {
+        self.contested.partition_point(|t| *t <= depth)
+    }
+
+    /// Like `complexity_within`, but also determines the size of the neighborhood.
+    fn stats_within(&self, depth : usize) -> NeighborhoodStats {
+        let markers = self.spans.partition_point(|(reach,_)| *reach <= depth);
+        NeighborhoodStats {
+            complexity : self.complexity_within(depth),
+            adjacencies : self.linked.partition_point(|reach| *reach <= depth),
+            markers,
+            span : if markers == 0 { 0 } else { self.spans[markers-1].1 }
+        }
+    }
+
+    /// Complexity (number of contested adjacencies) of the neighborhood of `m`.
+    pub fn complexity(&mut self, m : Marker, max_depth : usize, graph : &impl RearrangementGraph) -> usize {
+        self.expand(m, max_depth, Flank::Both, graph);
+        self.complexity_within(max_depth)
+    }
+
+    /// Complexities of the neighborhoods of `m` for each of the context lengths in `depths`,
+    /// calculated from a single expansion up to the largest context length.
+    pub fn complexities(&mut self, m : Marker, depths : &[usize], flank : Flank, graph : &impl RearrangementGraph) -> Vec<usize> {
+        self.expand(m, depths.iter().copied().max().unwrap_or(0), flank, graph);
+        depths.iter().map(|d| self.complexity_within(*d)).collect()
+    }
+
+    /// Like `complexities`, but also reports the size of each neighborhood.
+    pub fn neighborhood_stats(&mut self, m : Marker, depths : &[usize], flank : Flank, graph : &impl RearrangementGraph) -> Vec<NeighborhoodStats> {
+        self.expand(m, depths.iter().copied().max().unwrap_or(0), flank, graph);
+        depths.iter().map(|d| self.stats_within(*d)).collect()
+    }
+}
+
 /* 
 fn scan_partial(graph : &impl RearrangementGraph, max_depth :usize , markers : &[usize],thread_num : usize) -> HashMap<Marker,usize> {
     let mut node_complexities = HashMap::new();
@@ -86,7 +427,7 @@ fn scan_partial(graph : &impl RearrangementGraph, max_depth :usize , markers : &
         i+=1;
         if i%(about_ten_percent) == 0 {
             let percentage = i*100/tot_size;
-            eprintln!("Thread {thread_num} processed {i}/{tot_size} nodes ({percentage}%).");
+            log_info!("Thread {thread_num} processed {i}/{tot_size} nodes ({percentage}%).");
         }
         
     }
@@ -106,7 +447,7 @@ fn scan_graph_multithread(graph : &impl RearrangementGraph,max_depth :usize, n_t
         for i in 0..n_threads {
             let lb = slice_size*i;
             let rb = (slice_size*(i+1)).min(markerlist.len());
-            eprintln!("Spawning thread {i} processing markers with index {lb} to {rb} (total {totlen})");
+            log_debug!("Spawning thread {i} processing markers with index {lb} to {rb} (total {totlen})");
             let mlist = &markerlist[lb..rb];
             let x =  scope.spawn(move || scan_partial(graph, max_depth, mlist, i));
             handles.push(x);
@@ -123,57 +464,157 @@ fn scan_graph_multithread(graph : &impl RearrangementGraph,max_depth :usize, n_t
 */
 
 
-fn scan_enumerate(graph : &impl RearrangementGraph, max_depth :usize , start : usize, end : usize,thread_num : usize) -> HashMap<Marker,usize> {
-    let mut node_complexities = HashMap::new();
-    let tot_size = end - start;
-    let about_one_percent = (tot_size/100 +1).min(ONE_MILLION);
-    //eprintln!("Thread {thread_num}: {about_ten_percent}");
-    let mut i = 0;
+fn scan_enumerate(graph : &impl RearrangementGraph, max_depth :usize , start : usize, end : usize, workspace : &mut ScanWorkspace, node_complexities : &mut HashMap<Marker,usize>) {
     for m in start..end {
         if m == 0 || graph.adj_neighbors(head(m)).is_none(){
             continue;
         }
-        //eprintln!("Processing node {}",m);
-        let adjacencies = adjacency_neighborhood(m,max_depth, graph);
-        let ci = carp_measure_from_adjacencies(&adjacencies);
+        let ci = workspace.complexity(m, max_depth, graph);
         node_complexities.insert(m,ci);
-        i+=1;
-        if i%(about_one_percent) == 0 {
-            let percentage = i*100/tot_size;
-            eprintln!("Thread {thread_num} processed {i}/{tot_size} nodes ({percentage}%).");
-        }
-        
     }
-    node_complexities
 }
 
 pub fn scan_graph_enum_multithread(graph : &impl RearrangementGraph,max_depth :usize, n_threads : usize) -> HashMap<Marker, usize> {
     let mmax : Marker = graph.markers().max().unwrap_or(0)+1;
     let mut node_complexities = HashMap::new();
-    let totlen = mmax;
+    let workspace = ScanWorkspace::new(graph,ContextUnit::BasePairs);
+    let results = parallel_chunks(mmax, SCAN_CHUNK_SIZE, n_threads, "marker ids",
+        || (workspace.clone(),HashMap::new()),
+        |(ws,hm),lb,rb,_| scan_enumerate(graph, max_depth, lb, rb, ws, hm));
+    for (_,hm) in results {
+        node_complexities.extend(hm);
+    }
+    node_complexities
+}
+
+/// Like `scan_graph_enum_multithread`, but calculates the complexity and size of the neighborhood for each of
+/// the context lengths in `depths` from a single expansion per marker. The results of each marker are in the order of `depths`.
+pub fn scan_graph_multiscale(graph : &impl RearrangementGraph, depths : &[usize], unit : ContextUnit, flank : Flank, n_threads : usize) -> HashMap<Marker, Vec<NeighborhoodStats>> {
+    let mmax : Marker = graph.markers().max().unwrap_or(0)+1;
+    let mut node_complexities = HashMap::new();
+    let workspace = ScanWorkspace::new(graph,unit);
+    let results = parallel_chunks(mmax, SCAN_CHUNK_SIZE, n_threads, "marker ids",
+        || (workspace.clone(),HashMap::new()),
+        |(ws,hm) : &mut (ScanWorkspace,HashMap<Marker,Vec<NeighborhoodStats>>),lb,rb,_| {
+            for m in lb..rb {
+                if m == 0 || graph.adj_neighbors(head(m)).is_none(){
+                    continue;
+                }
+                hm.insert(m, ws.neighborhood_stats(m, depths, flank, graph));
+            }
+        });
+    for (_,hm) in results {
+        node_complexities.extend(hm);
+    }
+    node_complexities
+}
+
+/// Like `scan_graph_multiscale`, but only calculates the complexities of the given markers.
+pub fn scan_markers_multithread(graph : &impl RearrangementGraph, depths : &[usize], unit : ContextUnit, flank : Flank, markers : &[Marker], n_threads : usize) -> HashMap<Marker, Vec<NeighborhoodStats>> {
+    let mut node_complexities = HashMap::new();
+    let workspace = ScanWorkspace::new(graph,unit);
+    let results = parallel_chunks(markers.len(), SCAN_CHUNK_SIZE, n_threads, "markers",
+        || (workspace.clone(),HashMap::new()),
+        |(ws,hm) : &mut (ScanWorkspace,HashMap<Marker,Vec<NeighborhoodStats>>),lb,rb,_| {
+            for m in &markers[lb..rb] {
+                hm.insert(*m, ws.neighborhood_stats(*m, depths, flank, graph));
+            }
+        });
+    for (_,hm) in results {
+        node_complexities.extend(hm);
+    }
+    node_complexities
+}
+
+/// Like `scan_markers_multithread`, but instead of collecting the results, passes the neighborhoods of each
+/// marker to `sink` as soon as they are calculated, so that memory does not grow with the number of markers.
+/// The scanning threads send their results through a bounded channel, `sink` is called on the calling thread
+/// in the order in which the markers are completed. The first error of `sink` stops the scan, only the chunks
+/// of markers already being scanned are completed, and the error is returned.
+pub fn scan_markers_streaming<F>(graph : &impl RearrangementGraph, depths : &[usize], unit : ContextUnit, flank : Flank, markers : &[Marker], n_threads : usize, mut sink : F) -> io::Result<()>
+where
+    F : FnMut(Marker,Vec<NeighborhoodStats>) -> io::Result<()>
+{
+    let stop = AtomicBool::new(false);
     thread::scope(|scope| {
-        let mut handles = Vec::new();
-        let slice_size = totlen/n_threads +1;
-        for i in 0..n_threads {
-            let lb = slice_size*i;
-            let rb = (slice_size*(i+1)).min(totlen);
-            eprintln!("Spawning thread {i} processing markers with index {lb} to {rb} (total {totlen})");
-            let x =  scope.spawn(move || scan_enumerate(graph,max_depth,lb,rb,i));
-            handles.push(x);
+        let (sender,receiver) = mpsc::sync_channel::<Vec<(Marker,Vec<NeighborhoodStats>)>>(STREAM_BUFFER);
+        let stop = &stop;
+        let workspace = ScanWorkspace::new(graph,unit);
+        scope.spawn(move || {
+            parallel_chunks_until(markers.len(), SCAN_CHUNK_SIZE, n_threads, "markers", stop,
+                || workspace.clone(),
+                |ws : &mut ScanWorkspace,lb,rb,_| {
+                    let chunk = markers[lb..rb].iter().map(|m| (*m,ws.neighborhood_stats(*m, depths, flank, graph))).collect();
+                    //the consumer only hangs up after an error of the sink
+                    if sender.send(chunk).is_err() {
+                        stop.store(true, AtomicOrdering::Relaxed);
+                    }
+                });
+        });
+        for chunk in receiver {
+            for (m,stats) in chunk {
+                if let Err(e) = sink(m,stats) {
+                    stop.store(true, AtomicOrdering::Relaxed);
+                    return Err(e);
+                }
+            }
         }
-        for x in handles {
-            let hm = x.join().unwrap();
-            node_complexities.extend(hm);
+        Ok(())
+    })
+}
+
+/// Markers that `scan_graph_multiscale` calculates neighborhoods for, sorted by id.
+pub fn scannable_markers(graph : &impl RearrangementGraph) -> Vec<Marker> {
+    let mut markers : Vec<Marker> = graph.markers()
+        .filter(|m| *m != 0 && graph.adj_neighbors(head(*m)).is_some())
+        .collect();
+    markers.sort();
+    markers
+}
+
+/// Markers of shard `shard` (0-based) when splitting `markers` into `n_shards` shards. The partition
+/// only depends on the marker ids, so that every run on the same (trimmed) graph assigns the same shards.
+pub fn shard_markers(markers : &[Marker], shard : usize, n_shards : usize) -> Vec<Marker> {
+    markers.iter().filter(|m| *m % n_shards == shard).copied().collect()
+}
+
+/// Reads node names from a file with one name per line. Empty lines and lines starting with `#`
+/// are skipped, only the first (tab or space separated) field of each line is used.
+pub fn read_node_names(path : &str) -> io::Result<Vec<String>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut names = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') {
+            continue;
         }
-        
-    });
-    
-    node_complexities
+        if let Some(name) = line.split_whitespace().next() {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Resolves node names to the markers of the graph. Returns the markers (without duplicates,
+/// in the order of `names`) and the names that are not part of the (trimmed) graph.
+pub fn resolve_node_names(graph : &impl RearrangementGraph, names : &[String]) -> (Vec<Marker>,Vec<String>) {
+    let mut markers = Vec::new();
+    let mut seen = HashSet::new();
+    let mut unknown = Vec::new();
+    for name in names {
+        match graph.name_to_marker(name).filter(|m| graph.adj_neighbors(head(*m)).is_some()) {
+            Some(m) => if seen.insert(m) {
+                markers.push(m);
+            },
+            None => unknown.push(name.clone())
+        }
+    }
+    (markers,unknown)
 }
 
 
 pub fn scan_graph(graph : &impl RearrangementGraph,max_depth :usize) -> HashMap<Marker, usize>{
-    eprintln!("Scanning graph...");
+    log_info!("Scanning graph...");
     let mut node_complexities = HashMap::new();
     let tot_size = graph.num_markers();
     let mut i = 1;
@@ -193,7 +634,7 @@ pub fn scan_graph(graph : &impl RearrangementGraph,max_depth :usize) -> HashMap<
         
         if i%(about_one_percent) == 0 {
             let percentage = i*100/tot_size;
-            eprintln!("Processed {i}/{tot_size} nodes ({percentage}%).");
+            log_info!("Processed {i}/{tot_size} nodes ({percentage}%).");
         }
         i+=1;
     }
@@ -202,7 +643,7 @@ pub fn scan_graph(graph : &impl RearrangementGraph,max_depth :usize) -> HashMap<
 
 
 
-pub fn histogram(node_complexities : &HashMap<Marker,usize>) -> HashMap<usize,usize> {
+pub fn histogram<K>(node_complexities : &HashMap<K,usize>) -> HashMap<usize,usize> {
     let mut hist : HashMap<usize,usize> = HashMap::new();
     for (_, ci) in node_complexities {
         let count = hist.entry(*ci).or_insert(0);
@@ -211,37 +652,175 @@ pub fn histogram(node_complexities : &HashMap<Marker,usize>) -> HashMap<usize,us
     hist
 }
 
-pub fn top_percentile(node_complexities : &HashMap<Marker,usize>,percentile_low : f64,percentile_high : f64) -> Vec<Marker> {
-    let num_nodes = node_complexities.len();
-    let hist = histogram(&node_complexities);
-    let mut hist_entries : Vec<usize>= hist.keys().cloned().collect();
+/// Treatment of markers with equal complexity at the bounds of a quantile range `[lo,hi)`. Of `n` markers sorted
+/// by complexity, marker `i` has the rank `i/n` and covers the quantiles `[i/n,(i+1)/n)`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum TiePolicy {
+    /// Markers of equal complexity are selected together if the rank of the first of them lies in the range.
+    /// Consecutive ranges partition the markers, but a range can be empty if it lies within a tie group.
+    LowestRank,
+    /// Markers of equal complexity are selected together if any of them overlaps the range.
+    Include,
+    /// Markers of equal complexity are selected together if all of them lie within the range.
+    Exclude,
+    /// Exactly the markers whose rank lies in the range, ties are broken by key.
+    Exact
+}
+
+impl TiePolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TiePolicy::LowestRank => "lowest-rank",
+            TiePolicy::Include => "include",
+            TiePolicy::Exclude => "exclude",
+            TiePolicy::Exact => "exact"
+        }
+    }
+
+    pub fn from_name(name : &str) -> Option<Self> {
+        [TiePolicy::LowestRank,TiePolicy::Include,TiePolicy::Exclude,TiePolicy::Exact].into_iter().find(|t| t.name() == name)
+    }
+
+    /// Parses the `--ties` option of the command line programs.
+    pub fn parse(name : &str) -> Result<Self,String> {
+        TiePolicy::from_name(name).ok_or_else(|| format!("unknown tie policy {name}, use lowest-rank, include, exclude or exact"))
+    }
+
+    /// Whether the markers at positions `[a,b)` of `n` sorted markers, which have equal complexity, are selected
+    /// for the quantile range `[lo,hi)`. For `Exact`, whether any of them is selected.
+    fn selects(&self, a : usize, b : usize, n : usize, lo : f64, hi : f64) -> bool {
+        let (s,e) = rank_positions(n, lo, hi);
+        match self {
+            TiePolicy::LowestRank => a >= s && a < e,
+            TiePolicy::Include => (a as f64) < hi*n as f64 && (b as f64) > lo*n as f64,
+            TiePolicy::Exclude => (a as f64) >= lo*n as f64 && (b as f64) <= hi*n as f64,
+            TiePolicy::Exact => a.max(s) < b.min(e)
+        }
+    }
+}
+
+/// Positions `[s,e)` of the markers whose rank lies in `[lo,hi)`, out of `n` sorted markers.
+pub fn rank_positions(n : usize, lo : f64, hi : f64) -> (usize,usize) {
+    let position = |q : f64| ((q*n as f64).ceil().max(0.0) as usize).min(n);
+    (position(lo),position(hi))
+}
+
+/// Markers sorted by complexity, to look up the complexity at a quantile, the rank of a marker and the
+/// markers in a quantile range.
+#[derive(Debug,Clone)]
+pub struct Quantiles<K> {
+    /// Complexity and key of each marker, sorted by complexity, then key.
+    sorted : Vec<(usize,K)>,
+    /// Number of markers with a lower complexity.
+    ranks : HashMap<K,usize>
+}
+
+impl<K : Hash+Ord+Clone> Quantiles<K> {
+    pub fn new(node_complexities : &HashMap<K,usize>) -> Self {
+        let mut sorted : Vec<(usize,K)> = node_complexities.iter().map(|(k,c)| (*c,k.clone())).collect();
+        sorted.sort();
+        let mut ranks = HashMap::with_capacity(sorted.len());
+        let mut rank = 0;
+        for (i,(c,k)) in sorted.iter().enumerate() {
+            if i > 0 && sorted[i-1].0 != *c {
+                rank = i;
+            }
+            ranks.insert(k.clone(), rank);
+        }
+        Quantiles { sorted, ranks }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sorted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sorted.is_empty()
+    }
+
+    /// Complexity at quantile `q`, i.e. of the first marker whose rank is at least `q` (the last marker for
+    /// `q=1`). `None` if there are no markers.
+    pub fn value_at(&self, q : f64) -> Option<usize> {
+        let (i,_) = rank_positions(self.len(), q, q);
+        self.sorted.get(i.min(self.len().saturating_sub(1))).map(|(c,_)| *c)
+    }
+
+    /// Number of markers with a lower complexity than `key`.
+    pub fn rank(&self, key : &K) -> Option<usize> {
+        self.ranks.get(key).copied()
+    }
+
+    /// Fraction of markers with a lower complexity than `key`, as in `percentile_ranks`.
+    pub fn percentile_rank(&self, key : &K) -> Option<f64> {
+        self.rank(key).map(|r| r as f64/self.len() as f64)
+    }
+
+    /// Markers in the quantile range `[lo,hi)`, sorted by complexity, then key.
+    pub fn range(&self, lo : f64, hi : f64, ties : TiePolicy) -> Vec<K> {
+        let n = self.len();
+        let (s,e) = rank_positions(n, lo, hi);
+        let mut selected = Vec::new();
+        let mut a = 0;
+        for group in self.sorted.chunk_by(|x,y| x.0 == y.0) {
+            let b = a+group.len();
+            if ties == TiePolicy::Exact {
+                let (from,to) = (a.max(s),b.min(e));
+                if from < to {
+                    selected.extend(group[from-a..to-a].iter().map(|(_,k)| k.clone()));
+                }
+            } else if ties.selects(a, b, n, lo, hi) {
+                selected.extend(group.iter().map(|(_,k)| k.clone()));
+            }
+            a = b;
+        }
+        selected
+    }
+}
+
+/// Complexity range `[low,high)` of the markers in the quantile range `[lo,hi)` under the tie policy, given the
+/// histogram of complexities. For `Exact`, the range also covers the unselected markers of the boundary complexities.
+pub fn quantile_thresholds(hist : &HashMap<usize,usize>, lo : f64, hi : f64, ties : TiePolicy) -> Option<(usize,usize)> {
+    let n : usize = hist.values().sum();
+    let mut hist_entries : Vec<usize> = hist.keys().cloned().collect();
     hist_entries.sort();
-    let mut count = 0;
+    let mut a = 0;
     let mut thresh_low = None;
     let mut thresh_high = None;
     for e in hist_entries {
-        let high_enough = (count as f64) >= percentile_low * num_nodes as f64;
-        let not_too_high = (count as f64) < percentile_high * num_nodes as f64;
-        count+=hist.get(&e).unwrap();
-        if high_enough && not_too_high  {
-            if thresh_low.is_none() {
-                thresh_low = Some(e);
-            }
+        let b = a+hist[&e];
+        if ties.selects(a, b, n, lo, hi) {
+            thresh_low.get_or_insert(e);
             thresh_high = Some(e+1);
-        } else if !not_too_high  {
-            break;
-        }
-    }
-    //eprintln!("Lowthresh {thresh_low:?} Highthresh {thresh_high:?}");
-    let mut complex_nodes = Vec::new();
-    if let (Some(lt),Some(ht)) = (thresh_low,thresh_high) {
-        for (u,ci) in node_complexities {
-        if *ci < ht && *ci >= lt {
-            complex_nodes.push(*u);
         }
+        a = b;
     }
+    thresh_low.zip(thresh_high)
+}
+
+/// Complexity range `[low,high)` of the markers between the lower and higher percentile, given the histogram
+/// of complexities. Used by `top_percentile`, but also works on histograms that were collected without keeping
+/// the complexities of single markers.
+pub fn percentile_thresholds(hist : &HashMap<usize,usize>,percentile_low : f64,percentile_high : f64) -> Option<(usize,usize)> {
+    quantile_thresholds(hist, percentile_low, percentile_high, TiePolicy::LowestRank)
+}
+
+/// Markers between the lower and higher percentile with the `LowestRank` tie policy.
+pub fn top_percentile(node_complexities : &HashMap<Marker,usize>,percentile_low : f64,percentile_high : f64) -> Vec<Marker> {
+    Quantiles::new(node_complexities).range(percentile_low, percentile_high, TiePolicy::LowestRank)
+}
+
+/// Percentile rank of each marker, i.e. the fraction of markers with a lower complexity. A marker is
+/// output by `top_percentile` if its rank lies between the lower (inclusive) and higher (exclusive) percentile.
+pub fn percentile_ranks<K : Hash+Eq+Clone>(node_complexities : &HashMap<K,usize>) -> HashMap<K,f64> {
+    let num_nodes = node_complexities.len();
+    let hist = histogram(node_complexities);
+    let mut hist_entries : Vec<usize> = hist.keys().cloned().collect();
+    hist_entries.sort();
+    let mut lower = HashMap::new();
+    let mut count = 0;
+    for e in hist_entries {
+        lower.insert(e, count);
+        count+=hist[&e];
     }
-    
-    
-    complex_nodes
-}
\ No newline at end of file
+    node_complexities.iter().map(|(m,ci)| (m.clone(),lower[ci] as f64 / num_nodes as f64)).collect()
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use crate::rearrangement::*;
+use crate::mbg::MBG;
+use crate::scan::*;
+use crate::util::*;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct NullModelParams {
+    /// Number of randomized graphs to scan.
+    pub randomizations : usize,
+    /// Number of attempted adjacency swaps per adjacency, see `MBG::randomized`.
+    pub swaps_per_edge : usize,
+    pub seed : u64
+}
+
+/// Observed complexity of a marker compared to its complexities in randomized graphs.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Significance {
+    pub observed : usize,
+    pub mean : f64,
+    /// Standard deviation of the complexities in the randomized graphs.
+    pub sd : f64,
+    /// Empirical p-value `(1+r)/(1+n)` of observing at least the observed complexity, where `r` of the `n`
+    /// randomized graphs reach it.
+    pub p_value : f64
+}
+
+impl Significance {
+    /// `None` if the complexity does not vary between the randomized graphs.
+    pub fn z_score(&self) -> Option<f64> {
+        if self.sd > 0.0 {
+            Some((self.observed as f64-self.mean)/self.sd)
+        } else {
+            None
+        }
+    }
+}
+
+/// Compares the observed complexities (context length `depth`) against the complexities of the same markers
+/// in `params.randomizations` degree-preserving randomizations of `graph`. Each randomized graph is scanned
+/// and discarded before the next one is created, only sums per marker are kept.
+pub fn null_model_significance(graph : &MBG, observed : &HashMap<Marker,usize>, depth : usize, unit : ContextUnit, params : &NullModelParams, n_threads : usize) -> HashMap<Marker,Significance> {
+    let mut markers : Vec<Marker> = observed.keys().copied().collect();
+    markers.sort();
+    let mut rng = SplitMix64::new(params.seed);
+    //number of randomizations reaching the observed complexity, sum and sum of squares of complexities
+    let mut acc : HashMap<Marker,(usize,f64,f64)> = markers.iter().map(|m| (*m,(0,0.0,0.0))).collect();
+    for r in 0..params.randomizations {
+        log_info!("Scanning randomized graph {} of {}.",r+1,params.randomizations);
+        let randomized = graph.randomized(params.swaps_per_edge, &mut rng);
+        let scanned = scan_markers_multithread(&randomized, &[depth], unit, Flank::Both, &markers, n_threads);
+        for (m,ns) in scanned {
+            let c = ns[0].complexity;
+            let (reached,sum,sqsum) = acc.get_mut(&m).unwrap();
+            if c >= observed[&m] {
+                *reached += 1;
+            }
+            *sum += c as f64;
+            *sqsum += (c*c) as f64;
+        }
+    }
+    let n = params.randomizations as f64;
+    acc.into_iter().map(|(m,(reached,sum,sqsum))| {
+        let mean = if n > 0.0 { sum/n } else { 0.0 };
+        let var = if n > 0.0 { (sqsum/n-mean*mean).max(0.0) } else { 0.0 };
+        (m,Significance {
+            observed : observed[&m],
+            mean,
+            sd : var.sqrt(),
+            p_value : (1+reached) as f64/(1.0+n)
+        })
+    }).collect()
+}
+
+/// Writes the significance of each marker as tab separated table sorted by p-value, then node name.
+pub fn write_significance(graph : &impl RearrangementGraph, significance : &HashMap<Marker,Significance>, params : &NullModelParams, out : &mut impl Write) -> io::Result<()> {
+    let names = graph.marker_names();
+    let mut markers : Vec<(&Significance,&String)> = significance.iter().map(|(m,s)| (s,&names[m])).collect();
+    markers.sort_by(|a,b| a.0.p_value.total_cmp(&b.0.p_value).then(a.1.cmp(b.1)));
+    writeln!(out,"#Randomizations: {}",params.randomizations)?;
+    writeln!(out,"#Swaps per adjacency: {}",params.swaps_per_edge)?;
+    writeln!(out,"#Seed: {}",params.seed)?;
+    writeln!(out,"#Node\tSCJ-CARP-measure in env\tNull mean\tNull sd\tz-score\tp-value")?;
+    for (s,name) in markers {
+        let z = s.z_score().map(|z| format!("{z:.4}")).unwrap_or(String::from("*"));
+        writeln!(out,"{name}\t{}\t{:.4}\t{:.4}\t{z}\t{:.6}",s.observed,s.mean,s.sd,s.p_value)?;
+    }
+    Ok(())
+}
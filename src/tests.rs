@@ -6,6 +6,18 @@ use crate::measure::*;
 use crate::scan::*;
 use crate::ubg::*;
 use crate::mbg::*;
+use crate::cluster::*;
+use crate::dynamic::*;
+use crate::gfa::*;
+use crate::sample::*;
+use crate::compare::*;
+use crate::output::*;
+use crate::checkpoint::*;
+use crate::hotspot::*;
+use crate::reference::*;
+use crate::significance::*;
+use crate::color::*;
+use crate::histogram::*;
 
     #[test]
     fn test_hdtl() {
@@ -227,7 +239,8 @@ use crate::mbg::*;
         adj.insert(tail(1), HashSet::from([TELOMERE]));
         adj.insert(TELOMERE, HashSet::from([tail(1)]));
         let g = T::from_hash_maps(sizes, adj, nids);
-        let (a,b) = calc_carp_measure_multithread(&g, 1);
+        let report = calc_carp_measure_multithread(&g, 1);
+        let (a,b) = (report.contested, report.uncontested);
         carp_sanity_check(&g, &a.iter().copied().collect(), &b.iter().copied().collect());
         let mut aexp = Vec::new();
         aexp.push((head(1),head(1)));
@@ -372,8 +385,8 @@ use crate::mbg::*;
             ubg.fill_telomeres();
             let mut mbg = MBG::from_gfa(gfafile,true).expect("Y");
             mbg.fill_telomeres();
-            let (c,uc) = calc_carp_measure_naive(&ubg);
-            let (cc,ucc) = calc_carp_measure_naive(&mbg);
+            let (c,uc) = naive_sets(&ubg);
+            let (cc,ucc) = naive_sets(&mbg);
             let ac = carp_measure_from_adjacencies(&ubg.iter_adjacencies().collect());
             let acc = carp_measure_from_adjacencies(&mbg.iter_adjacencies().collect());
             eprintln!("diff ja lol ey {:?}",c.symmetric_difference(&cc));
@@ -472,7 +485,8 @@ use crate::mbg::*;
 
     fn carp_multithread_sanity_check(ubg : &impl RearrangementGraph, contested : &HashSet<Adjacency>, uncontested : &HashSet<Adjacency>) {
         for i in 1..8 {
-            let (c, uc) = calc_carp_measure_multithread(ubg, i);
+            let report = calc_carp_measure_multithread(ubg, i);
+            let (c, uc) = (report.contested, report.uncontested);
             assert_eq!(find_dups(&c),Vec::new());
             assert_eq!(find_dups(&uc),Vec::new());
             eprintln!("El difference: {:?}",contested.symmetric_difference(&c.iter().copied().collect()));
@@ -485,6 +499,11 @@ use crate::mbg::*;
         }
     }
 
+    fn naive_sets(graph : &impl RearrangementGraph) -> (HashSet<Adjacency>,HashSet<Adjacency>) {
+        let report = calc_carp_measure_naive(graph);
+        (report.contested.into_iter().collect(),report.uncontested.into_iter().collect())
+    }
+
     fn carp_sanity_check(ubg : &impl RearrangementGraph, contested : &HashSet<Adjacency>, uncontested : &HashSet<Adjacency>) {
         let mut all = HashSet::new();
         for (x,y) in ubg.iter_adjacencies() {
@@ -518,8 +537,8 @@ use crate::mbg::*;
     fn test_carp_eva() {
         let ubg = UBG::from_gfa("testfiles/test02.gfa",true).expect("File should be readable");
         let mbg = MBG::from_gfa("testfiles/test02.gfa",true).expect("File should be readable");
-        let (contested,uncontested) = calc_carp_measure_naive(&ubg);
-        let (mcontested,muncontested) = calc_carp_measure_naive(&mbg);
+        let (contested,uncontested) = naive_sets(&ubg);
+        let (mcontested,muncontested) = naive_sets(&mbg);
         assert_eq!(contested,mcontested);
         assert_eq!(uncontested,muncontested);
         carp_sanity_check(&ubg, &contested, &uncontested);
@@ -814,7 +833,73 @@ fn test_percentile() {
 
 }
 
+#[test]
+fn test_percentile_ranks() {
+    let complexities : HashMap<Marker,usize> = [(1,2),(2,40),(3,2),(4,3),(5,6),(6,1),(7,4),(8,5),(9,5),(10,5)].into_iter().collect();
+    let ranks = percentile_ranks(&complexities);
+    assert_eq!(ranks[&6],0.0);
+    assert_eq!(ranks[&1],0.1);
+    assert_eq!(ranks[&3],0.1);
+    assert_eq!(ranks[&8],0.5);
+    assert_eq!(ranks[&2],0.9);
+    for (lo,hi) in [(0.0,0.3),(0.0,1.0),(0.1,0.2),(0.2,0.5),(0.8,1.0),(0.9,1.0)] {
+        let tps : HashSet<Marker> = top_percentile(&complexities, lo, hi).into_iter().collect();
+        let exp : HashSet<Marker> = ranks.iter().filter(|(_,r)| **r >= lo && **r < hi).map(|(m,_)| *m).collect();
+        assert_eq!(tps,exp);
+    }
+}
+
+
 
+#[test]
+fn test_quantiles() {
+    let complexities : HashMap<Marker,usize> = [(1,2),(2,40),(3,2),(4,3),(5,6),(6,1),(7,4),(8,5),(9,5),(10,5)].into_iter().collect();
+    let q = Quantiles::new(&complexities);
+    assert_eq!(q.len(),10);
+    assert_eq!((q.value_at(0.0),q.value_at(0.5),q.value_at(0.55),q.value_at(1.0)),(Some(1),Some(5),Some(5),Some(40)));
+    assert_eq!((q.rank(&8),q.rank(&9),q.rank(&2),q.rank(&11)),(Some(5),Some(5),Some(9),None));
+    assert_eq!(q.percentile_rank(&3),Some(0.1));
+    //the complexity 5 covers the quantiles [0.5,0.8)
+    assert_eq!(q.range(0.6, 0.9, TiePolicy::LowestRank),vec![5]);
+    assert_eq!(q.range(0.6, 0.7, TiePolicy::LowestRank),vec![]);
+    assert_eq!(q.range(0.6, 0.7, TiePolicy::Include),vec![8,9,10]);
+    assert_eq!(q.range(0.4, 1.0, TiePolicy::Include),vec![7,8,9,10,5,2]);
+    assert_eq!(q.range(0.5, 0.9, TiePolicy::Exclude),vec![8,9,10,5]);
+    assert_eq!(q.range(0.6, 0.8, TiePolicy::Exclude),vec![]);
+    assert_eq!(q.range(0.6, 0.9, TiePolicy::Exact),vec![9,10,5]);
+    assert_eq!(q.range(0.65, 0.7, TiePolicy::Exact),vec![]);
+    assert_eq!(TiePolicy::parse("exact"),Ok(TiePolicy::Exact));
+    assert!(TiePolicy::parse("lowest").is_err());
+    for (lo,hi) in [(0.0,0.3),(0.0,1.0),(0.1,0.2),(0.2,0.5),(0.8,1.0),(0.9,1.0)] {
+        let mut tp = top_percentile(&complexities, lo, hi);
+        tp.sort();
+        let mut range = q.range(lo, hi, TiePolicy::LowestRank);
+        range.sort();
+        assert_eq!(tp,range);
+        assert_eq!(q.range(lo, hi, TiePolicy::Exact).len(),(hi*10.0).round() as usize-(lo*10.0).round() as usize);
+    }
+    //exact ranges partition the markers
+    let parts : Vec<Marker> = [0.0,0.25,0.5,0.75,1.0].windows(2).flat_map(|w| q.range(w[0], w[1], TiePolicy::Exact)).collect();
+    assert_eq!(parts,vec![6,1,3,4,7,8,9,10,5,2]);
+    //thresholds from the histogram agree with the selection
+    let hist = histogram(&complexities);
+    assert_eq!(quantile_thresholds(&hist, 0.6, 0.7, TiePolicy::Include),Some((5,6)));
+    assert_eq!(quantile_thresholds(&hist, 0.6, 0.7, TiePolicy::LowestRank),None);
+    assert_eq!(quantile_thresholds(&hist, 0.6, 0.9, TiePolicy::Exact),Some((5,7)));
+    //tiny inputs
+    let empty : Quantiles<Marker> = Quantiles::new(&HashMap::new());
+    assert_eq!((empty.value_at(0.5),empty.range(0.0, 1.0, TiePolicy::Include)),(None,vec![]));
+    let single = Quantiles::new(&[(1,7)].into_iter().collect());
+    assert_eq!((single.value_at(0.0),single.value_at(1.0)),(Some(7),Some(7)));
+    assert_eq!(single.range(0.9, 1.0, TiePolicy::LowestRank),vec![]);
+    assert_eq!(single.range(0.9, 1.0, TiePolicy::Include),vec![1]);
+    assert_eq!(single.range(0.0, 1.0, TiePolicy::Exclude),vec![1]);
+    let tied = Quantiles::new(&[(1,3),(2,3),(3,3)].into_iter().collect());
+    assert_eq!(tied.range(0.5, 1.0, TiePolicy::LowestRank),vec![]);
+    assert_eq!(tied.range(0.0, 0.5, TiePolicy::LowestRank),vec![1,2,3]);
+    assert_eq!(tied.range(0.5, 1.0, TiePolicy::Exact),vec![3]);
+    assert_eq!(tied.range(0.0, 0.5, TiePolicy::Exact),vec![1,2]);
+}
 
 #[test]
 fn test_overlap_environment() {
@@ -856,4 +941,752 @@ fn test_overlap_environment() {
     assert_eq!(expect,res);
     let res = adjacency_neighborhood(m1,1000, &graph);
     assert_eq!(expect,res);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_conflict_clusters() {
+    let path = "testfiles/test15.gfa";
+    let mut mbg = MBG::from_gfa(path, true).unwrap();
+    mbg.fill_telomeres();
+    let contested = calc_carp_measure_multithread(&mbg, 2).contested;
+    let mut clusters = conflict_clusters(&mbg, &contested);
+    let m : Vec<Marker> = (1..=5).map(|i| mbg.name_to_marker(&i.to_string()).unwrap()).collect();
+    //path ends make (3h,4t) and (4h,5t) contested as well
+    assert_eq!(clusters.len(),4);
+    let mut expect = vec![canonicize((head(m[0]),tail(m[1]))),canonicize((head(m[0]),tail(m[2]))),canonicize((head(m[1]),tail(m[2])))];
+    expect.sort();
+    assert_eq!(clusters[0].adjacencies,expect);
+    assert_eq!(clusters[0].markers,vec![m[0],m[1],m[2]]);
+    assert_eq!(clusters[0].length,60);
+    assert_eq!(clusters[1].adjacencies,vec![canonicize((head(m[2]),tail(m[3])))]);
+    assert_eq!(clusters[3].adjacencies,vec![(head(m[4]),head(m[4]))]);
+    assert_eq!(clusters[3].length,50);
+    let of_interest = contested.iter().copied().collect();
+    let genomes = genome_adjacencies_gfa(path, &mbg, &of_interest).unwrap();
+    annotate_genomes(&mut clusters, &genomes);
+    assert_eq!(clusters[0].genomes,["A".to_string(),"B".to_string()].into_iter().collect());
+    assert_eq!(clusters[1].genomes,["B".to_string()].into_iter().collect());
+    assert_eq!(clusters[2].genomes,["C".to_string()].into_iter().collect());
+    assert_eq!(clusters[3].genomes,["D".to_string()].into_iter().collect());
+}
+
+
+#[test]
+fn test_carp_report() {
+    let mut mbg = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    mbg.fill_telomeres();
+    let report = calc_carp_measure_multithread(&mbg, 3);
+    assert_eq!(report.markers,5);
+    assert_eq!(report.extremities,11);
+    assert_eq!(report.measure(),6);
+    assert_eq!(report.adjacencies,mbg.iter_adjacencies().count());
+    assert_eq!(report.telomeric_adjacencies,6);
+    assert_eq!(report.self_loops,1);
+    assert_eq!(report.degree_distribution.values().sum::<usize>(),10);
+    assert_eq!(report.measure_per_adjacency(),1.0);
+    assert_eq!(report.measure_per_marker(),1.2);
+    assert!(report.to_json().contains("\"contested\": 6"));
+}
+
+
+#[test]
+fn test_compare_measures() {
+    let mut mbg = MBG::from_gfa("testfiles/random/test_1.gfa", true).unwrap();
+    mbg.fill_telomeres();
+    let mut ubg = UBG::from_gfa("testfiles/random/test_1.gfa", true).unwrap();
+    ubg.fill_telomeres();
+    let report = calc_carp_measure_multithread(&mbg, 4);
+    assert!(report_duplicates(&mbg, &report).is_empty());
+    assert!(compare_measures(&mbg, &report, &ubg, &calc_carp_measure_naive(&ubg)).is_empty());
+    let naive = calc_carp_measure_naive(&mbg);
+    assert_eq!((naive.measure(),naive.adjacencies,naive.telomeric_adjacencies,naive.self_loops),
+        (report.measure(),report.adjacencies,report.telomeric_adjacencies,report.self_loops));
+    assert_eq!(naive.degree_distribution,report.degree_distribution);
+    let mut moved_report = report.clone();
+    let moved = moved_report.contested.pop().unwrap();
+    moved_report.uncontested.push(moved);
+    let diff = compare_measures(&mbg, &report, &mbg, &moved_report);
+    let names = mbg.marker_names();
+    assert_eq!(diff.contested_only_left.len(),1);
+    assert_eq!(diff.uncontested_only_right.len(),1);
+    assert!(diff.contested_only_right.is_empty() && diff.uncontested_only_left.is_empty());
+    assert!(diff.contested_only_left[0].contains(names.get(&marker(moved.0)).unwrap().as_str()));
+    //paths and walks end at telomeres in both backends
+    for f in ["testfiles/test12.gfa","testfiles/test15.gfa","testfiles/test16.gfa"] {
+        let mut mbg = MBG::from_gfa(f, true).unwrap();
+        mbg.fill_telomeres();
+        let mut ubg = UBG::from_gfa(f, true).unwrap();
+        ubg.fill_telomeres();
+        let naive = calc_carp_measure_naive(&mbg);
+        assert_eq!(naive.measure(),calc_carp_measure_multithread(&mbg, 2).measure());
+        assert!(compare_measures(&mbg, &naive, &ubg, &calc_carp_measure_naive(&ubg)).is_empty(),"{f}");
+    }
+}
+
+
+#[test]
+fn test_dynamic_measure() {
+    for i in read_dir("testfiles/random/").expect("W") {
+        let gfafile = i.unwrap().path();
+        let mut mbg = MBG::from_gfa(gfafile.to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        let mut dm = DynamicMeasure::new(mbg.clone());
+        for flt in 0..10 {
+            dm.trim(flt);
+            let report = calc_carp_measure_multithread(dm.graph(), 2);
+            assert_eq!(dm.measure(),report.measure());
+            assert_eq!(dm.uncontested(),report.uncontested.len());
+            let mut trimmed = mbg.clone();
+            trimmed.trim_singlethread(flt);
+            assert_eq!(dm.measure(),calc_carp_measure_multithread(&trimmed, 2).measure());
+        }
+        let xtrs : Vec<Extremity> = dm.graph().extremities().collect();
+        for (j,x) in xtrs.iter().enumerate() {
+            let y = xtrs[(7*j+3)%xtrs.len()];
+            dm.add_adjacency(*x, y);
+            let report = calc_carp_measure_multithread(dm.graph(), 2);
+            assert_eq!(dm.measure(),report.measure());
+            assert_eq!(dm.uncontested(),report.uncontested.len());
+        }
+    }
+    //unimog graphs have no node sizes, removal must still work
+    let mut mbg = MBG::from_unimog("testfiles/test01.ug").unwrap();
+    mbg.fill_telomeres();
+    let mut dm = DynamicMeasure::new(mbg);
+    let m = dm.graph().markers().next().unwrap();
+    dm.remove_marker(m);
+    assert_eq!(dm.graph().degree(head(m)),None);
+    assert_eq!(dm.measure(),calc_carp_measure_multithread(dm.graph(), 2).measure());
+}
+
+
+#[test]
+fn test_parallel_chunks() {
+    for total in [0,1,5,100,1031] {
+        for chunk_size in [1,7,64] {
+            for n_threads in 1..5 {
+                let results = parallel_chunks(total, chunk_size, n_threads, "items", Vec::new, |v : &mut Vec<usize>,lb,rb,_| v.extend(lb..rb));
+                assert_eq!(results.len(),n_threads);
+                let mut all : Vec<usize> = results.into_iter().flatten().collect();
+                all.sort();
+                assert_eq!(all,(0..total).collect::<Vec<_>>());
+            }
+        }
+    }
+    //no further chunks are fetched once the stop flag is set
+    let stop = std::sync::atomic::AtomicBool::new(false);
+    let results = parallel_chunks_until(1000, 10, 1, "items", &stop, Vec::new, |v : &mut Vec<usize>,lb,rb,_| {
+        v.extend(lb..rb);
+        if rb >= 30 {
+            stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    });
+    assert_eq!(results,vec![(0..30).collect::<Vec<_>>()]);
+}
+
+
+#[test]
+fn test_scan_workspace() {
+    let mut graphs = Vec::new();
+    for i in read_dir("testfiles/random/").expect("W") {
+        let mut mbg = MBG::from_gfa(i.unwrap().path().to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        graphs.push(mbg);
+    }
+    for (path,ignore_overlap) in [("testfiles/test12.gfa",true),("testfiles/test14.gfa",false),("testfiles/test15.gfa",true)] {
+        let mut mbg = MBG::from_gfa(path, ignore_overlap).unwrap();
+        mbg.fill_telomeres();
+        graphs.push(mbg);
+    }
+    for g in &graphs {
+        let mut ws = ScanWorkspace::new(g,ContextUnit::BasePairs);
+        for depth in [0,5,30,77,200,1000] {
+            for m in g.markers() {
+                let expect = carp_measure_from_adjacencies(&adjacency_neighborhood(m, depth, g));
+                assert_eq!(ws.complexity(m, depth, g),expect);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_scan_multiscale() {
+    let depths = [1000,0,5,30,77,200];
+    for i in read_dir("testfiles/random/").expect("W") {
+        let mut mbg = MBG::from_gfa(i.unwrap().path().to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        let multiscale = scan_graph_multiscale(&mbg, &depths, ContextUnit::BasePairs, Flank::Both, 2);
+        for (k,depth) in depths.iter().enumerate() {
+            let single : HashMap<Marker,usize> = multiscale.iter().map(|(m,ns)| (*m,ns[k].complexity)).collect();
+            assert_eq!(single,scan_graph(&mbg, *depth));
+        }
+        let m = mbg.markers().next().unwrap();
+        let reach = adjacency_neighborhood_reach(m, 1000, ContextUnit::BasePairs, Flank::Both, &mbg);
+        for depth in depths {
+            let within : HashSet<Adjacency> = reach.iter().filter(|(_,r)| **r <= depth).map(|(a,_)| *a).collect();
+            assert_eq!(within,adjacency_neighborhood(m, depth, &mbg));
+        }
+    }
+}
+
+#[test]
+fn test_context_unit() {
+    //unimog graphs have no node sizes, so both units agree
+    let mut graph = MBG::from_unimog("testfiles/test05.ug").unwrap();
+    graph.fill_telomeres();
+    let depths = [0,1,2,5];
+    assert_eq!(scan_graph_multiscale(&graph, &depths, ContextUnit::Nodes, Flank::Both, 1),scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Both, 1));
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let m = graph.name_to_marker("1").unwrap();
+    let names = graph.marker_names();
+    let reached = |depth| -> HashSet<String> {
+        adjacency_neighborhood_reach(m, depth, ContextUnit::Nodes, Flank::Both, &graph).into_keys()
+            .flat_map(|(x,y)| [x,y]).filter(|x| *x != TELOMERE)
+            .map(|x| names[&marker(x)].clone()).collect()
+    };
+    assert_eq!(reached(0),HashSet::from(["1".to_string(),"2".to_string(),"3".to_string()]));
+    assert_eq!(reached(1),HashSet::from(["1".to_string(),"2".to_string(),"3".to_string(),"4".to_string()]));
+    let nodes = scan_graph_multiscale(&graph, &depths, ContextUnit::Nodes, Flank::Both, 2);
+    for (k,depth) in depths.iter().enumerate() {
+        for m in graph.markers() {
+            let expect = carp_measure_from_adjacencies(&adjacency_neighborhood_reach(m, *depth, ContextUnit::Nodes, Flank::Both, &graph).into_keys().collect());
+            assert_eq!(nodes[&m][k].complexity,expect);
+        }
+    }
+}
+
+#[test]
+fn test_neighborhood_stats() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let m = graph.name_to_marker("3").unwrap();
+    let mut ws = ScanWorkspace::new(&graph, ContextUnit::BasePairs);
+    //only the adjacencies of 3 itself: 1-3, 2-3 and 3-4, all contested
+    let stats = ws.neighborhood_stats(m, &[15,1000], Flank::Both, &graph);
+    assert_eq!(stats[0],NeighborhoodStats { complexity : 3, adjacencies : 3, markers : 4, span : 100 });
+    assert_eq!(stats[0].normalized(),1.0);
+    //the whole graph: 5 non-telomeric adjacencies plus the self loop
+    assert_eq!(stats[1].adjacencies,6);
+    assert_eq!(stats[1].markers,5);
+    assert_eq!(stats[1].span,150);
+    for i in read_dir("testfiles/random/").expect("W") {
+        let mut mbg = MBG::from_gfa(i.unwrap().path().to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        let scan = scan_graph_multiscale(&mbg, &[77], ContextUnit::BasePairs, Flank::Both, 1);
+        for m in mbg.markers() {
+            let adjacencies = adjacency_neighborhood(m, 77, &mbg);
+            let non_telomeric : Vec<&Adjacency> = adjacencies.iter().filter(|(x,y)| *x != TELOMERE && *y != TELOMERE).collect();
+            let markers : HashSet<Marker> = non_telomeric.iter().flat_map(|(x,y)| [marker(*x),marker(*y)]).collect();
+            let stats = scan[&m][0];
+            assert_eq!(stats.complexity,carp_measure_from_adjacencies(&adjacencies));
+            assert_eq!(stats.adjacencies,non_telomeric.len());
+            assert_eq!(stats.markers,markers.len());
+            assert_eq!(stats.span,markers.iter().map(|m| mbg.node_size(*m).unwrap_or(1)).sum::<usize>());
+        }
+    }
+    //the order of the context lengths does not matter
+    assert_eq!(ws.neighborhood_stats(m, &[1000,15], Flank::Both, &graph),vec![stats[1],stats[0]]);
+    for i in read_dir("testfiles/random/").expect("W") {
+        let mut mbg = MBG::from_gfa(i.unwrap().path().to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        let mut ws_random = ScanWorkspace::new(&mbg, ContextUnit::BasePairs);
+        for m in mbg.markers() {
+            let ascending = ws_random.neighborhood_stats(m, &[0,30,200], Flank::Both, &mbg);
+            let descending = ws_random.neighborhood_stats(m, &[200,30,0], Flank::Both, &mbg);
+            assert_eq!(ascending,descending.into_iter().rev().collect::<Vec<_>>());
+        }
+    }
+}
+
+#[test]
+fn test_contested_thresholds() {
+    //one exploration answers every context length up to the largest one
+    let depths : Vec<usize> = (0..=120).step_by(8).collect();
+    for i in read_dir("testfiles/random/").expect("W") {
+        let mut mbg = MBG::from_gfa(i.unwrap().path().to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        let mut ws = ScanWorkspace::new(&mbg, ContextUnit::BasePairs);
+        for m in mbg.markers() {
+            let complexities = ws.complexities(m, &depths, Flank::Both, &mbg);
+            for (depth,complexity) in depths.iter().zip(complexities) {
+                assert_eq!(complexity,carp_measure_from_adjacencies(&adjacency_neighborhood(m, *depth, &mbg)));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_extremity_degrees() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let degrees = extremity_degrees(&graph);
+    let m = graph.name_to_marker("3").unwrap();
+    assert_eq!(degrees[tail(m)],2);
+    //4 and the end of path A
+    assert_eq!(degrees[head(m)],2);
+    //the self loop counts twice, the walk ends at 5 as well
+    assert_eq!(degrees[head(graph.name_to_marker("5").unwrap())],3);
+    for i in read_dir("testfiles/random/").expect("W") {
+        let mut mbg = MBG::from_gfa(i.unwrap().path().to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        let adjacencies : HashSet<Adjacency> = mbg.iter_adjacencies().map(canonicize).collect();
+        let mut expected = vec![0;mbg.markers().max().map(head).unwrap_or(0)+1];
+        for (x,y) in adjacencies {
+            //a self loop counts twice
+            expected[x]+=1;
+            expected[y]+=1;
+        }
+        expected[TELOMERE] = 0;
+        assert_eq!(extremity_degrees(&mbg),expected);
+    }
+}
+
+#[test]
+fn test_flanks() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let m = graph.name_to_marker("3").unwrap();
+    let mut ws = ScanWorkspace::new(&graph, ContextUnit::BasePairs);
+    //the tail of 3 is adjacent to 1 and 2, the head of 3 to 4 whose tail is a path end
+    assert_eq!(ws.complexities(m, &[15], Flank::Both, &graph),vec![3]);
+    assert_eq!(ws.complexities(m, &[15], Flank::Head, &graph),vec![1]);
+    assert_eq!(ws.complexities(m, &[15], Flank::Tail, &graph),vec![2]);
+    let depths = [0,5,30,200];
+    for i in read_dir("testfiles/random/").expect("W") {
+        let mut mbg = MBG::from_gfa(i.unwrap().path().to_str().unwrap(), true).unwrap();
+        mbg.fill_telomeres();
+        for flank in [Flank::Head,Flank::Tail] {
+            let scan = scan_graph_multiscale(&mbg, &depths, ContextUnit::BasePairs, flank, 2);
+            for (k,depth) in depths.iter().enumerate() {
+                for m in mbg.markers() {
+                    let adjacencies = adjacency_neighborhood_reach(m, *depth, ContextUnit::BasePairs, flank, &mbg).into_keys().collect();
+                    assert_eq!(scan[&m][k].complexity,carp_measure_from_adjacencies(&adjacencies));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sample_scan() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let depths = [0,30,1000];
+    let full = scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Both, 1);
+    let params = SamplingParams { size : SampleSize::Fraction(1.0), unit : ContextUnit::BasePairs, weighting : SampleWeighting::Uniform, seed : 1 };
+    let sampled = sample_scan(&graph, &depths, &params, 2);
+    assert_eq!(sampled.neighborhoods,full);
+    assert_eq!(sampled.sampled,full.len());
+    assert!(sampled.anchor_distance.values().all(|d| *d == 0));
+    let mut report = Vec::new();
+    write_sampling_report(&graph, &params, &depths, &sampled, &mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    assert!(report.starts_with("#Parameter\tValue\nMarkers\t5\n"));
+    assert!(report.ends_with("#Node\tAnchor\tDistance\n1\t1\t0\n2\t2\t0\n3\t3\t0\n4\t4\t0\n5\t5\t0\n"));
+    for weighting in [SampleWeighting::Uniform,SampleWeighting::Length] {
+        let params = SamplingParams { size : SampleSize::Count(2), unit : ContextUnit::BasePairs, weighting, seed : 42 };
+        let a = sample_scan(&graph, &[30], &params, 1);
+        let b = sample_scan(&graph, &[30], &params, 3);
+        assert_eq!(a.neighborhoods,b.neighborhoods);
+        assert_eq!(a.anchor_of,b.anchor_of);
+        assert_eq!(a.sampled,2);
+        assert_eq!(a.neighborhoods.len(),graph.num_markers());
+        let full = scan_graph_enum_multithread(&graph, 30, 1);
+        for (m,anchor) in &a.anchor_of {
+            assert_eq!(a.neighborhoods[m][0].complexity,full[anchor]);
+        }
+    }
+    //components without sampled anchor get their own anchor
+    let mut graph = MBG::from_unimog("testfiles/test05.ug").unwrap();
+    graph.fill_telomeres();
+    let params = SamplingParams { size : SampleSize::Count(0), unit : ContextUnit::Nodes, weighting : SampleWeighting::Uniform, seed : 0 };
+    let sampled = sample_scan(&graph, &[3], &params, 1);
+    assert_eq!(sampled.sampled,0);
+    assert!(sampled.component_anchors > 1);
+    assert_eq!(sampled.neighborhoods.len(),graph.num_markers());
+}
+
+#[test]
+fn test_compare_scans() {
+    let left : HashMap<String,usize> = [("a",1),("b",2),("c",3),("d",4)].into_iter().map(|(n,c)| (n.to_string(),c)).collect();
+    let right : HashMap<String,usize> = [("a",1),("b",5),("c",0),("e",4)].into_iter().map(|(n,c)| (n.to_string(),c)).collect();
+    let cmp = compare_scans(&left, &right);
+    let order : Vec<&str> = cmp.shared.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(order,vec!["b","c","a"]);
+    assert_eq!(cmp.only_left,vec!["d".to_string()]);
+    assert_eq!(cmp.only_right,vec!["e".to_string()]);
+    assert_eq!((cmp.increased(),cmp.decreased()),(1,1));
+    assert_eq!(cmp.mean_difference(),0.0);
+    assert_eq!(cmp.mean_absolute_difference(),2.0);
+    //b moves from rank 1/4 to the top rank 3/4, c from 2/4 to the bottom
+    assert_eq!(cmp.shared[0].rank_shift(),0.5);
+    assert_eq!(cmp.shared[1].rank_shift(),-0.5);
+
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let scan = scan_graph_enum_multithread(&graph, 30, 1);
+    let named = named_complexities(&graph, &scan);
+    let cmp = compare_scans(&named, &named);
+    assert_eq!(cmp.shared.len(),graph.num_markers());
+    assert!(cmp.only_left.is_empty() && cmp.only_right.is_empty());
+    assert_eq!(cmp.mean_absolute_rank_shift(),0.0);
+}
+
+#[test]
+fn test_resolve_node_names() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    graph.trim_singlethread(25);
+    graph.fill_telomeres();
+    let names : Vec<String> = ["3","5","unknown","1","3"].iter().map(|s| s.to_string()).collect();
+    let (markers,unknown) = resolve_node_names(&graph, &names);
+    assert_eq!(markers,vec![graph.name_to_marker("3").unwrap(),graph.name_to_marker("5").unwrap()]);
+    //1 is trimmed away
+    assert_eq!(unknown,vec!["unknown".to_string(),"1".to_string()]);
+    let subset = scan_markers_multithread(&graph, &[100], ContextUnit::BasePairs, Flank::Both, &markers, 2);
+    let full = scan_graph_multiscale(&graph, &[100], ContextUnit::BasePairs, Flank::Both, 1);
+    assert_eq!(subset.len(),2);
+    for m in markers {
+        assert_eq!(subset[&m],full[&m]);
+    }
+}
+
+#[test]
+fn test_shard_merge() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let depths = [15,40];
+    let mut full = ScanResults::new(&depths, ContextUnit::BasePairs, scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Both, 1));
+    full.scans.push((Flank::Head,scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Head, 1)));
+    full.scans.push((Flank::Tail,scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Tail, 1)));
+    let markers = scannable_markers(&graph);
+    let mut merged : Option<ScanResults> = None;
+    let mut n_sharded = 0;
+    for i in 0..3 {
+        let shard = shard_markers(&markers, i, 3);
+        n_sharded += shard.len();
+        let mut results = ScanResults::new(&depths, ContextUnit::BasePairs, scan_markers_multithread(&graph, &depths, ContextUnit::BasePairs, Flank::Both, &shard, 2));
+        for flank in [Flank::Head,Flank::Tail] {
+            results.scans.push((flank,scan_markers_multithread(&graph, &depths, ContextUnit::BasePairs, flank, &shard, 2)));
+        }
+        let mut table = Vec::new();
+        results.write_table(&graph, &mut table).unwrap();
+        let path = std::env::temp_dir().join(format!("carp_test_shard_{}_{i}.tsv",std::process::id()));
+        std::fs::write(&path, table).unwrap();
+        let read = read_scan_results(&graph, path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        match &mut merged {
+            None => merged = Some(read),
+            Some(m) => m.merge(read).unwrap()
+        }
+    }
+    assert_eq!(n_sharded,markers.len());
+    let merged = merged.unwrap();
+    assert_eq!(merged.node_complexities(),full.node_complexities());
+    let (mut a,mut b) = (Vec::new(),Vec::new());
+    full.write_table(&graph, &mut a).unwrap();
+    merged.write_table(&graph, &mut b).unwrap();
+    assert_eq!(a,b);
+    let (mut a,mut b) = (Vec::new(),Vec::new());
+    full.write_percentiles(&graph, 0.5, 1.0, TiePolicy::LowestRank, &mut a).unwrap();
+    merged.write_percentiles(&graph, 0.5, 1.0, TiePolicy::LowestRank, &mut b).unwrap();
+    assert_eq!(a,b);
+    let other = ScanResults::new(&[15], ContextUnit::BasePairs, HashMap::new());
+    assert!(merged.clone().merge(other).is_err());
+    //a shard merged twice
+    let mut twice = merged.clone();
+    assert!(twice.merge(merged.clone()).is_err());
+    assert_eq!(twice.node_complexities(),full.node_complexities());
+}
+
+#[test]
+fn test_checkpoint_resume() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let depths = [15,40];
+    let markers = scannable_markers(&graph);
+    let path = std::env::temp_dir().join(format!("carp_test_checkpoint_{}.tsv",std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut checkpoint = Checkpoint::create(path, &depths, ContextUnit::BasePairs, 2).unwrap();
+    let both = checkpoint.scan(&graph, Flank::Both, &markers, 2).unwrap();
+    assert_eq!(both,scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Both, 1));
+    checkpoint.scan(&graph, Flank::Head, &markers[..1], 1).unwrap();
+    drop(checkpoint);
+    //simulate an interruption while writing a record
+    let mut content = std::fs::read_to_string(path).unwrap();
+    content.push_str("head\t3\t1,1");
+    std::fs::write(path, content).unwrap();
+    assert!(Checkpoint::resume(path, &graph, &[15], ContextUnit::BasePairs, 2).is_err());
+    let mut checkpoint = Checkpoint::resume(path, &graph, &depths, ContextUnit::BasePairs, 2).unwrap();
+    assert_eq!(checkpoint.completed(Flank::Both),markers.len());
+    assert_eq!(checkpoint.completed(Flank::Head),1);
+    assert_eq!(checkpoint.scan(&graph, Flank::Both, &markers, 1).unwrap(),both);
+    let head_flank = checkpoint.scan(&graph, Flank::Head, &markers, 1).unwrap();
+    assert_eq!(head_flank,scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Head, 1));
+    drop(checkpoint);
+    let resumed = Checkpoint::resume(path, &graph, &depths, ContextUnit::BasePairs, 2).unwrap();
+    assert_eq!(resumed.completed(Flank::Head),markers.len());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_streaming_scan() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let depths = [15,40];
+    let markers = scannable_markers(&graph);
+    let full = scan_graph_multiscale(&graph, &depths, ContextUnit::BasePairs, Flank::Both, 1);
+    let mut streamed = HashMap::new();
+    scan_markers_streaming(&graph, &depths, ContextUnit::BasePairs, Flank::Both, &markers, 3, |m,stats| {
+        assert!(streamed.insert(m,stats).is_none());
+        Ok(())
+    }).unwrap();
+    assert_eq!(streamed,full);
+    let mut calls = 0;
+    let res = scan_markers_streaming(&graph, &depths, ContextUnit::BasePairs, Flank::Both, &markers, 2, |_,_| {
+        calls += 1;
+        Err(std::io::Error::other("full disk"))
+    });
+    assert!(res.is_err());
+    assert_eq!(calls,1);
+    //histogram and percentiles from the streamed table agree with the in-memory results
+    let path = std::env::temp_dir().join(format!("carp_test_stream_{}.tsv",std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut table = TableStream::new(&graph, &depths, ContextUnit::BasePairs, Vec::new()).unwrap();
+    scan_markers_streaming(&graph, &depths, ContextUnit::BasePairs, Flank::Both, &markers, 2, |m,stats| table.write(&graph, m, &stats)).unwrap();
+    std::fs::write(path, table.finish().unwrap()).unwrap();
+    let results = ScanResults::new(&depths, ContextUnit::BasePairs, full);
+    assert_eq!(table_histogram(path).unwrap(),histogram(&results.node_complexities()));
+    let sorted = |v : Vec<u8>| { let mut l : Vec<String> = String::from_utf8(v).unwrap().lines().map(|l| l.to_string()).collect(); l.sort(); l };
+    for ties in [TiePolicy::LowestRank,TiePolicy::Include,TiePolicy::Exclude,TiePolicy::Exact] {
+        let (mut a,mut b) = (Vec::new(),Vec::new());
+        results.write_percentiles(&graph, 0.2, 0.9, ties, &mut a).unwrap();
+        write_table_percentiles(path, 0.2, 0.9, ties, &mut b).unwrap();
+        assert_eq!(sorted(a),sorted(b));
+    }
+    assert_eq!(read_scan_results(&graph, path).unwrap().scans,results.scans);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_call_hotspots() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let m = |name : &str| graph.name_to_marker(name).unwrap();
+    let node_c : HashMap<Marker,usize> = scan_graph_multiscale(&graph, &[15], ContextUnit::BasePairs, Flank::Both, 1).into_iter()
+        .map(|(m,ns)| (m,ns[0].complexity)).collect();
+    let hotspots = call_hotspots(&graph, &node_c, HotspotCutoff::Complexity(2), 0, ContextUnit::BasePairs);
+    assert_eq!(hotspots.len(),1);
+    let mut members = vec![m("1"),m("3")];
+    members.sort();
+    assert_eq!(hotspots[0],Hotspot { markers : members, length : 40, peak : 3, mean : 2.5, representative : m("3") });
+    let top = call_hotspots(&graph, &node_c, HotspotCutoff::Percentile(0.7,TiePolicy::LowestRank), 0, ContextUnit::BasePairs);
+    assert_eq!(top.len(),1);
+    assert_eq!(top[0].markers,vec![m("3")]);
+    //the hotspot markers are the ones of the percentile output under every tie policy
+    let tied : HashMap<Marker,usize> = [(m("1"),1),(m("2"),3),(m("3"),3),(m("4"),3),(m("5"),0)].into_iter().collect();
+    let results = ScanResults::new(&[15], ContextUnit::BasePairs, tied.iter().map(|(m,c)| (*m,vec![NeighborhoodStats { complexity : *c, ..Default::default() }])).collect());
+    for ties in [TiePolicy::LowestRank,TiePolicy::Include,TiePolicy::Exclude,TiePolicy::Exact] {
+        let mut out = Vec::new();
+        results.write_percentiles(&graph, 0.5, 1.0, ties, &mut out).unwrap();
+        let mut listed : Vec<Marker> = String::from_utf8(out).unwrap().lines().filter(|l| !l.starts_with('#')).map(|l| m(l.split('\t').next().unwrap())).collect();
+        listed.sort();
+        let mut hot : Vec<Marker> = call_hotspots(&graph, &tied, HotspotCutoff::Percentile(0.5,ties), 100, ContextUnit::BasePairs).into_iter().flat_map(|h| h.markers).collect();
+        hot.sort();
+        assert_eq!(hot,listed,"{}",ties.name());
+    }
+    //1 and 4 are separated by 3 (30bp) or 2 and 3 (50bp)
+    let apart : HashMap<Marker,usize> = [(m("1"),5),(m("2"),0),(m("3"),0),(m("4"),4),(m("5"),0)].into_iter().collect();
+    assert_eq!(call_hotspots(&graph, &apart, HotspotCutoff::Complexity(4), 29, ContextUnit::BasePairs).len(),2);
+    let merged = call_hotspots(&graph, &apart, HotspotCutoff::Complexity(4), 30, ContextUnit::BasePairs);
+    assert_eq!(merged.len(),1);
+    assert_eq!((merged[0].peak,merged[0].representative,merged[0].length),(5,m("1"),50));
+    assert_eq!(call_hotspots(&graph, &apart, HotspotCutoff::Complexity(4), 0, ContextUnit::Nodes).len(),2);
+    assert_eq!(call_hotspots(&graph, &apart, HotspotCutoff::Complexity(4), 1, ContextUnit::Nodes).len(),1);
+    assert!(call_hotspots(&graph, &apart, HotspotCutoff::Complexity(6), 100, ContextUnit::Nodes).is_empty());
+}
+
+#[test]
+fn test_reference_projection() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let (paths,lengths) = reference_paths_gfa("testfiles/test15.gfa", "A").unwrap();
+    assert_eq!(paths.len(),1);
+    assert_eq!(paths,reference_paths_gfa("testfiles/test15.gfa", "A#0#chr1").unwrap().0);
+    assert_eq!(paths[0].sequence,"chr1");
+    let projected = project_reference(&graph, &paths, &lengths).unwrap();
+    let coords : Vec<(usize,usize)> = projected[0].intervals.iter().map(|i| (i.start,i.end)).collect();
+    assert_eq!(coords,vec![(0,10),(10,30),(30,60)]);
+    let (walks,_) = reference_paths_gfa("testfiles/test15.gfa", "C").unwrap();
+    assert_eq!(walks,vec![ReferencePath { sequence : "chr1".to_string(), start : 0, steps : vec![("4".to_string(),true),("5".to_string(),true)] }]);
+    let node_c : HashMap<Marker,usize> = scan_graph_multiscale(&graph, &[15], ContextUnit::BasePairs, Flank::Both, 1).into_iter()
+        .map(|(m,ns)| (m,ns[0].complexity)).collect();
+    let mut bedgraph = Vec::new();
+    write_bedgraph(&projected, &node_c, &mut bedgraph).unwrap();
+    assert!(String::from_utf8(bedgraph).unwrap().ends_with("chr1\t0\t10\t2\nchr1\t10\t30\t0\nchr1\t30\t60\t3\n"));
+    let hotspots = call_hotspots(&graph, &node_c, HotspotCutoff::Complexity(2), 0, ContextUnit::BasePairs);
+    let mut bed = Vec::new();
+    write_hotspot_bed(&projected, &hotspots, 0, &mut bed).unwrap();
+    assert_eq!(String::from_utf8(bed).unwrap(),"chr1\t0\t10\thotspot_0\nchr1\t30\t60\thotspot_0\n");
+    let mut bed = Vec::new();
+    write_hotspot_bed(&projected, &hotspots, 20, &mut bed).unwrap();
+    assert_eq!(String::from_utf8(bed).unwrap(),"chr1\t0\t60\thotspot_0\n");
+    //the contigs of different haplotypes are told apart, fragments of a contig are sorted
+    let (walks,lengths) = reference_paths_gfa("testfiles/test16.gfa", "E").unwrap();
+    let sequences : Vec<&str> = walks.iter().map(|w| w.sequence.as_str()).collect();
+    assert_eq!(sequences,vec!["2#chr1","1#chr1","2#chr1"]);
+    assert_eq!(reference_paths_gfa("testfiles/test16.gfa", "E#1#chr1").unwrap().0[0].sequence,"chr1");
+    let projected = project_reference(&graph, &walks, &lengths).unwrap();
+    let mut bedgraph = Vec::new();
+    write_bedgraph(&projected, &node_c, &mut bedgraph).unwrap();
+    assert!(String::from_utf8(bedgraph).unwrap().ends_with("\n1#chr1\t0\t10\t2\n1#chr1\t10\t30\t0\n2#chr1\t0\t10\t2\n2#chr1\t10\t30\t0\n2#chr1\t30\t60\t3\n"));
+    let mut bed = Vec::new();
+    write_hotspot_bed(&projected, &hotspots, 0, &mut bed).unwrap();
+    assert_eq!(String::from_utf8(bed).unwrap(),"1#chr1\t0\t10\thotspot_0\n2#chr1\t0\t10\thotspot_0\n2#chr1\t30\t60\thotspot_0\n");
+}
+
+#[test]
+fn test_null_model() {
+    let mut graph = MBG::from_gfa("testfiles/random/test_1.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let randomized = graph.randomized(10, &mut SplitMix64::new(3));
+    for x in graph.extremities() {
+        assert_eq!(graph.degree(x),randomized.degree(x));
+    }
+    for m in graph.markers() {
+        assert_eq!(graph.node_size(m),randomized.node_size(m));
+    }
+    let original : HashSet<Adjacency> = graph.iter_adjacencies().collect();
+    let rewired : HashSet<Adjacency> = randomized.iter_adjacencies().collect();
+    assert_eq!(original.len(),rewired.len());
+    assert_ne!(original,rewired);
+    let again : HashSet<Adjacency> = graph.randomized(10, &mut SplitMix64::new(3)).iter_adjacencies().collect();
+    assert_eq!(rewired,again);
+    let node_c : HashMap<Marker,usize> = scan_graph_multiscale(&graph, &[100], ContextUnit::BasePairs, Flank::Both, 1).into_iter()
+        .map(|(m,ns)| (m,ns[0].complexity)).collect();
+    let params = NullModelParams { randomizations : 5, swaps_per_edge : 5, seed : 1 };
+    let significance = null_model_significance(&graph, &node_c, 100, ContextUnit::BasePairs, &params, 2);
+    assert_eq!(significance.len(),node_c.len());
+    for (m,s) in &significance {
+        assert_eq!(s.observed,node_c[m]);
+        assert!(s.p_value > 1.0/6.0-1e-9 && s.p_value <= 1.0);
+        if let Some(z) = s.z_score() {
+            assert!((z*s.sd+s.mean-s.observed as f64).abs() < 1e-6);
+        }
+    }
+    assert_eq!(significance,null_model_significance(&graph, &node_c, 100, ContextUnit::BasePairs, &params, 1));
+    let none = NullModelParams { randomizations : 0, ..params };
+    assert!(null_model_significance(&graph, &node_c, 100, ContextUnit::BasePairs, &none, 1).values().all(|s| s.p_value == 1.0 && s.z_score().is_none()));
+}
+
+#[test]
+fn test_color_map() {
+    let node_c : HashMap<Marker,usize> = [(1,0),(2,0),(3,3),(4,7),(5,15)].into_iter().collect();
+    let log = ColorMapParams::default().fit(&node_c);
+    assert_eq!((log.lo,log.hi),(0,15));
+    //the minimum used to be mapped to log2(0)
+    assert_eq!(log.position(0),0.0);
+    assert!((log.position(3)-0.5).abs() < 1e-9);
+    assert_eq!(log.position(15),1.0);
+    assert_eq!(log.color(0),to_heat_html(0.0));
+    assert!((log.value_at(log.position(7))-7.0).abs() < 1e-9);
+    let linear = ColorMapParams { scale : ColorScale::Linear, palette : Palette::Grayscale, min : Some(5), max : Some(10) }.fit(&node_c);
+    assert_eq!(linear.position(0),0.0);
+    assert_eq!(linear.position(7),0.4);
+    assert_eq!(linear.position(15),1.0);
+    assert_eq!(linear.color(0),"#e0e0e0");
+    assert_eq!(linear.color(15),"#000000");
+    assert_eq!(linear.value_at(0.4),7.0);
+    let rank = ColorMapParams { scale : ColorScale::Rank, palette : Palette::Viridis, min : None, max : None }.fit(&node_c);
+    let positions : Vec<f64> = [0,3,7,15].iter().map(|c| rank.position(*c)).collect();
+    assert_eq!(positions,vec![0.0,0.5,0.75,1.0]);
+    assert_eq!(rank.value_at(0.5),3.0);
+    assert_eq!(rank.color(15),"#fde725");
+    let flat = ColorMapParams::default().fit(&[(1,4)].into_iter().collect());
+    assert_eq!(flat.position(4),0.0);
+    assert_eq!(Palette::BlueRed.color(0.5),"#dddddd");
+    assert_eq!(ColorScale::from_name("rank"),Some(ColorScale::Rank));
+    assert_eq!(Palette::from_name("viridis"),Some(Palette::Viridis));
+    let mut svg = Vec::new();
+    rank.write_legend_svg("SCJ-CARP-measure", &mut svg).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains(">15</text>"));
+}
+
+#[test]
+fn test_bandage_annotations() {
+    let mut graph = MBG::from_gfa("testfiles/test15.gfa", true).unwrap();
+    graph.fill_telomeres();
+    let results = ScanResults::new(&[15], ContextUnit::BasePairs, scan_graph_multiscale(&graph, &[15], ContextUnit::BasePairs, Flank::Both, 1));
+    let colors = ColorMapParams::default().fit(&results.node_complexities());
+    let mut csv = Vec::new();
+    write_bandage_csv(&graph, &results, &colors, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines : Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(),6);
+    assert_eq!(lines[0],"Name,Color,SCJ-CARP-measure,Normalized SCJ-CARP-measure");
+    assert_eq!(lines[3],format!("3,{},3,1.0000",colors.color(3)));
+    let annotations = results.annotations(&colors);
+    let mut gfa = Vec::new();
+    assert_eq!(annotate_gfa(&graph, &annotations, "testfiles/test15.gfa", &mut gfa).unwrap(),5);
+    let gfa = String::from_utf8(gfa).unwrap();
+    let original = std::fs::read_to_string("testfiles/test15.gfa").unwrap();
+    assert_eq!(gfa.lines().count(),original.lines().count());
+    for (annotated,line) in gfa.lines().zip(original.lines()) {
+        if line.starts_with("S\t") {
+            let m = graph.name_to_marker(line.split('\t').nth(1).unwrap()).unwrap();
+            assert_eq!(annotated,format!("{line}\t{}",annotations[&m]));
+        } else {
+            assert_eq!(annotated,line);
+        }
+    }
+    //annotating again replaces the tags
+    let path = std::env::temp_dir().join(format!("carp_test_annotated_{}.gfa",std::process::id()));
+    std::fs::write(&path, &gfa).unwrap();
+    let mut again = Vec::new();
+    annotate_gfa(&graph, &annotations, path.to_str().unwrap(), &mut again).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(String::from_utf8(again).unwrap(),gfa);
+    //only tags with the same name are replaced, not tags whose name merely starts the same
+    let m1 = graph.name_to_marker("1").unwrap();
+    std::fs::write(&path, "S\t1\t*\tLN:i:10\tcrpx:i:7\tCL:z:red\tcr:i:1\n").unwrap();
+    let mut tagged = Vec::new();
+    annotate_gfa(&graph, &annotations, path.to_str().unwrap(), &mut tagged).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(String::from_utf8(tagged).unwrap(),format!("S\t1\t*\tLN:i:10\tcrpx:i:7\tcr:i:1\t{}\n",annotations[&m1]));
+}
+
+#[test]
+fn test_histogram() {
+    let hist : HashMap<usize,usize> = [(100,1),(5,3),(1,2),(20,1),(2,1)].into_iter().collect();
+    let sorted = bucket_histogram(&hist, None, false);
+    assert_eq!(sorted.iter().map(|b| (b.lo,b.count)).collect::<Vec<_>>(),vec![(1,2),(2,1),(5,3),(20,1),(100,1)]);
+    let mut table = Vec::new();
+    write_histogram(&sorted, false, &mut table).unwrap();
+    assert_eq!(String::from_utf8(table).unwrap(),"1\t2\n2\t1\n5\t3\n20\t1\n100\t1\n");
+    //requested buckets keep their format when they hold single complexities
+    let mut table = Vec::new();
+    write_histogram(&bucket_histogram(&[(4,2),(5,1)].into_iter().collect(), Some(10), false), true, &mut table).unwrap();
+    assert_eq!(String::from_utf8(table).unwrap(),"#From\tTo\tNodes\n4\t5\t2\n5\t6\t1\n");
+    let linear = bucket_histogram(&hist, Some(4), false);
+    assert_eq!(linear.iter().map(|b| (b.lo,b.hi,b.count)).collect::<Vec<_>>(),vec![(1,26,7),(26,51,0),(51,76,0),(76,101,1)]);
+    let log = bucket_histogram(&hist, Some(4), true);
+    assert_eq!(log.iter().map(|b| (b.lo,b.hi,b.count)).collect::<Vec<_>>(),vec![(1,3,3),(3,10,3),(10,32,1),(32,101,1)]);
+    let mut table = Vec::new();
+    write_histogram(&log, true, &mut table).unwrap();
+    assert!(String::from_utf8(table).unwrap().starts_with("#From\tTo\tNodes\n1\t3\t3\n"));
+    //more buckets than complexities
+    assert_eq!(bucket_histogram(&[(4,2),(5,1)].into_iter().collect(), Some(10), false).len(),2);
+    let markers = percentile_markers(&hist, 0.5, 1.0, TiePolicy::LowestRank);
+    assert_eq!(markers.len(),2);
+    assert!(markers[0].0 <= markers[1].0);
+    assert_eq!((markers[0].1.as_str(),markers[1].1.as_str()),("0.5","1"));
+    let mut svg = Vec::new();
+    write_histogram_svg(&linear, true, "complexity", &markers, &mut svg).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<rect").count(),4);
+    assert_eq!(svg.matches("stroke-dasharray").count(),2);
+}
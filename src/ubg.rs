@@ -1,5 +1,5 @@
 use crate::rearrangement::*;
-use crate::gfa::{LEN_PREFIX,get_or_set_node_id,parse_marker};
+use crate::gfa::{LEN_PREFIX,get_or_set_node_id,parse_marker,path_end_telomeres};
 use crate::util::{reverse_map};
 use std::collections::{HashMap, HashSet};
 use csv::{ReaderBuilder};
@@ -149,7 +149,7 @@ impl RearrangementGraph for UBG  {
     if !ignore_overlap {
         panic!("Not implemented.");
     }
-    eprintln!("Read gfa.");
+    log_info!("Read gfa.");
     let mut node_sizes = HashMap::new();
     let mut adjacencies = HashMap::new(); 
     let mut node_ids: HashMap<String, Marker>   = HashMap::new();
@@ -157,12 +157,13 @@ impl RearrangementGraph for UBG  {
     let mut curr_id = 1;
     let mut i :u32 = 0;
     let mut n_edges :usize = 0;
+    let mut telomeres : HashSet<(String,bool)> = HashSet::new();
     for res in rdr.records() {
         let x = res?;
         i+=1;
         if i%1000000==0 {
-            eprintln!("Read {} lines.",i);
-            eprintln!("{} nodes and {} edges in graph.",node_sizes.len(),n_edges);
+            log_info!("Read {} lines.",i);
+            log_info!("{} nodes and {} edges in graph.",node_sizes.len(),n_edges);
         }
   
         let entrytype= match x.get(0) {
@@ -219,12 +220,21 @@ impl RearrangementGraph for UBG  {
             adjacencies.get_mut(&axtr).expect("Horror").insert(bxtr);
             adjacencies.get_mut(&bxtr).expect("Horror").insert(axtr);
             n_edges+=1;
+        } else if entrytype == "P" || entrytype == "W" {
+            telomeres.extend(path_end_telomeres(&x).into_iter().flatten());
         }
 
     }
-    println!("GFA file parsing done.");
-    println!("Read {} lines.",i);
-    println!("{} nodes and {} edges in graph.",node_sizes.len(),n_edges);
+    //like the MBG backend, paths and walks end at telomeres
+    for (mrk,xtr_is_tail) in telomeres {
+        let m = *node_ids.get(&mrk).unwrap_or_else(|| panic!("Segment {mrk} occurs in a path, but not as a segment entry."));
+        let xtr = if xtr_is_tail { tail(m) } else { head(m) };
+        adjacencies.entry(xtr).or_insert(HashSet::new()).insert(TELOMERE);
+        adjacencies.entry(TELOMERE).or_insert(HashSet::new()).insert(xtr);
+    }
+    log_info!("GFA file parsing done.");
+    log_info!("Read {} lines.",i);
+    log_info!("{} nodes and {} edges in graph.",node_sizes.len(),n_edges);
     
     Ok(UBG {
         node_sizes,
@@ -258,7 +268,7 @@ fn from_unimog(path : &str) -> io::Result<UBG> {
         while let Some(j) =  line.find(" ") {
             let m = &line[0..j];
             
-            eprintln!("{}",m);
+            log_debug!("{}",m);
             let is_forward;
             let marker;
             (curr_id,is_forward,marker) = parse_marker(&mut node_ids, m, curr_id);
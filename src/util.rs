@@ -1,5 +1,7 @@
 use std::collections::{HashMap,HashSet};
 use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
 use crate::rearrangement::*;
 
 pub const SAFE_GFA_OVERLAP : bool = true;
@@ -95,4 +97,93 @@ pub fn is_my_adjacency((x,y) : Adjacency) -> bool {
     let hx = naive_hash(x);
     let hy = naive_hash(y);
     hx < hy || (hx == hy && x <= y)
-}
\ No newline at end of file
+}
+
+/// Processes the index range `0..total` with `n_threads` threads. Instead of assigning each
+/// thread a fixed slice, threads repeatedly fetch the next chunk of `chunk_size` indices from a
+/// shared counter, so that threads working on expensive regions do not hold up the others.
+/// Each thread folds the chunks it processed into its own accumulator created by `init`,
+/// `work` is called with the accumulator, the chunk bounds and the thread number.
+/// Progress on `what` is logged in steps of about one percent of `total`.
+pub fn parallel_chunks<A,I,F>(total : usize, chunk_size : usize, n_threads : usize, what : &str, init : I, work : F) -> Vec<A>
+where
+    A : Send,
+    I : Fn() -> A + Sync,
+    F : Fn(&mut A, usize, usize, usize) + Sync
+{
+    parallel_chunks_until(total, chunk_size, n_threads, what, &AtomicBool::new(false), init, work)
+}
+
+/// Like `parallel_chunks`, but threads do not fetch further chunks once `stop` is set, e.g. by `work`
+/// or by the consumer of its results after an error. Chunks that are already being processed are completed.
+pub fn parallel_chunks_until<A,I,F>(total : usize, chunk_size : usize, n_threads : usize, what : &str, stop : &AtomicBool, init : I, work : F) -> Vec<A>
+where
+    A : Send,
+    I : Fn() -> A + Sync,
+    F : Fn(&mut A, usize, usize, usize) + Sync
+{
+    let next_chunk = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let chunk_size = chunk_size.max(1);
+    thread::scope(|scope| {
+        let mut handles = Vec::new();
+        for i in 0..n_threads.max(1) {
+            let (next_chunk,done,init,work) = (&next_chunk,&done,&init,&work);
+            handles.push(scope.spawn(move || {
+                let mut acc = init();
+                let mut thread_done = 0;
+                loop {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let lb = next_chunk.fetch_add(chunk_size, Ordering::Relaxed);
+                    if lb >= total {
+                        break;
+                    }
+                    let rb = (lb+chunk_size).min(total);
+                    work(&mut acc, lb, rb, i);
+                    thread_done += rb-lb;
+                    let before = done.fetch_add(rb-lb, Ordering::Relaxed);
+                    if before*100/total != (before+rb-lb)*100/total {
+                        let percentage = (before+rb-lb)*100/total;
+                        log_info!("Processed {percentage}% of {total} {what} (thread {i} processed {thread_done}).");
+                    }
+                }
+                log_debug!("Thread {i} finished after processing {thread_done} {what}.");
+                acc
+            }));
+        }
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// Small seeded pseudo random number generator (SplitMix64), so that results depending on
+/// randomness can be reproduced from the seed alone.
+#[derive(Debug,Clone)]
+pub struct SplitMix64 {
+    state : u64
+}
+
+impl SplitMix64 {
+    pub fn new(seed : u64) -> Self {
+        SplitMix64 { state : seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in `[0,1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed in `0..n`, `n` must be positive.
+    pub fn below(&mut self, n : usize) -> usize {
+        (self.next_f64()*n as f64) as usize
+    }
+}
@@ -14,7 +14,7 @@ def get_run_result(filename):
         for line in f:
             if line.startswith(TRIGGER_MEASURE):
                 ca = int(line.strip().removeprefix(TRIGGER_MEASURE))
-            if line.startswith(TRIGGER_MARKER_B) and line.endswith(TRIGGER_MARKER_A):
+            if line.startswith(TRIGGER_MARKER_B) and line.strip().endswith(TRIGGER_MARKER_A):
                 mr = int(line.strip().removeprefix(TRIGGER_MARKER_B).removesuffix(TRIGGER_MARKER_A))
     return nm,mr,ca
 